@@ -0,0 +1,1111 @@
+use anyhow::{anyhow, Result};
+use time::format_description;
+
+use crate::{
+    agenda_view::AgendaView,
+    board_view::{self, BoardView},
+    calendar_view::CalendarView,
+    dates::{now, parse_date, parse_start},
+    keybindings::BindingContext,
+    list_view::ListView,
+    navigation::{Cursor, HierarchyItemEnum, HierarchyItemEnumMut, PositionHierarchy},
+    prompt::{prompt_date, prompt_date_in_place, Input},
+    settings::Settings,
+    todo_config::{new_id, Group, Priority, Todo, TodoConfig},
+    workflow,
+};
+
+// Everything the user can do. Keys, mouse clicks, the command line and tests all go through
+// `dispatch`, so an action only has to be written once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    AddTodo,
+    AddGroup,
+    AddTopGroup,
+    ToggleGroup,
+    ToggleTodo,
+    ArchiveTodo,
+    HideGroup,
+    EditTodo,
+    EditGroup,
+    MoveTodoUp,
+    MoveTodoDown,
+    MoveGroupUp,
+    MoveGroupDown,
+    CursorUp,
+    CursorDown,
+    GroupUp,
+    GroupDown,
+    HierarchyUp,
+    HierarchyDown,
+    Quit,
+    QuitWithoutSaving,
+    Save,
+    Clean,
+    Help,
+    CommandPalette,
+    MoveTo,   // Moves the item under the cursor into the group at a "Top/Sub" path
+    SetDue,   // Sets the due date from text like "tomorrow" or "2023-03-01 14:30"
+    Snooze,   // Defers a todo until a time like "1h", "tomorrow" or "next week"
+    Activate, // Toggles whatever is under the cursor, used for clicks
+    ToggleDebug,
+    CycleStatus,  // On to the next of settings.statuses
+    SetStatus,    // Picks a status by name, the only way to cancel
+    FilterStatus, // Lists only the todos in some statuses
+    TreeView,     // Back to the whole tree from another view
+    BoardView,    // The group under the cursor as a column for each status
+    CursorLeft,
+    CursorRight,
+    MoveLeft, // To the status before, or the column to the left on a board
+    MoveRight,
+    CalendarView, // Todos on the days they are due, again for a week instead of a month
+    AgendaView,   // Overdue todos, then what is due over the next week, then high priority ones
+    SetPriority,
+    ZoomIn,        // Shows just the group under the cursor, as if it were the top
+    ZoomOut,       // Back out a level from the zoomed in group
+    Custom(usize), // The nth of settings.custom_commands
+}
+
+// What the caller has to do after an action, for the things that live outside the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    Changed, // The data changed and isn't saved yet
+    Save,
+    Quit,
+    QuitWithoutSaving,
+    ShowHelp,
+    ShowPalette,
+    RunCustom(usize),
+    ToggleDebug,
+}
+
+impl Action {
+    pub const ALL: [Action; 44] = [
+        Action::AddTodo,
+        Action::AddGroup,
+        Action::AddTopGroup,
+        Action::ToggleGroup,
+        Action::ToggleTodo,
+        Action::ArchiveTodo,
+        Action::HideGroup,
+        Action::EditTodo,
+        Action::EditGroup,
+        Action::MoveTodoUp,
+        Action::MoveTodoDown,
+        Action::MoveGroupUp,
+        Action::MoveGroupDown,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::GroupUp,
+        Action::GroupDown,
+        Action::HierarchyUp,
+        Action::HierarchyDown,
+        Action::Quit,
+        Action::QuitWithoutSaving,
+        Action::Save,
+        Action::Clean,
+        Action::Help,
+        Action::CommandPalette,
+        Action::MoveTo,
+        Action::SetDue,
+        Action::Snooze,
+        Action::Activate,
+        Action::ToggleDebug,
+        Action::CycleStatus,
+        Action::SetStatus,
+        Action::FilterStatus,
+        Action::TreeView,
+        Action::BoardView,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::CalendarView,
+        Action::AgendaView,
+        Action::SetPriority,
+        Action::ZoomIn,
+        Action::ZoomOut,
+    ];
+
+    // Same as the field in Keybindings
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::AddTodo => "add_todo",
+            Action::AddGroup => "add_group",
+            Action::AddTopGroup => "add_top_group",
+            Action::ToggleGroup => "toggle_group",
+            Action::ToggleTodo => "toggle_todo",
+            Action::ArchiveTodo => "archive_todo",
+            Action::HideGroup => "hide_group",
+            Action::EditTodo => "edit_todo",
+            Action::EditGroup => "edit_group",
+            Action::MoveTodoUp => "move_todo_up",
+            Action::MoveTodoDown => "move_todo_down",
+            Action::MoveGroupUp => "move_group_up",
+            Action::MoveGroupDown => "move_group_down",
+            Action::CursorUp => "cursor_up",
+            Action::CursorDown => "cursor_down",
+            Action::GroupUp => "group_up",
+            Action::GroupDown => "group_down",
+            Action::HierarchyUp => "hierarchy_up",
+            Action::HierarchyDown => "hierarchy_down",
+            Action::Quit => "quit",
+            Action::QuitWithoutSaving => "quit_without_saving",
+            Action::Save => "save",
+            Action::Clean => "clean",
+            Action::Help => "help",
+            Action::CommandPalette => "command_palette",
+            Action::MoveTo => "move_to",
+            Action::SetDue => "set_due",
+            Action::Snooze => "snooze",
+            Action::Activate => "activate",
+            Action::ToggleDebug => "toggle_debug",
+            Action::CycleStatus => "cycle_status",
+            Action::SetStatus => "set_status",
+            Action::FilterStatus => "filter_status",
+            Action::TreeView => "tree_view",
+            Action::BoardView => "board_view",
+            Action::CursorLeft => "cursor_left",
+            Action::CursorRight => "cursor_right",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::CalendarView => "calendar_view",
+            Action::AgendaView => "agenda_view",
+            Action::SetPriority => "set_priority",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::Custom(_) => "custom", // Custom commands are named in settings.ron
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    pub fn context(self) -> BindingContext {
+        match self {
+            Action::AddTodo
+            | Action::AddGroup
+            | Action::ToggleGroup
+            | Action::HideGroup
+            | Action::EditGroup
+            | Action::MoveGroupUp
+            | Action::MoveGroupDown => BindingContext::Group,
+            Action::ToggleTodo
+            | Action::ArchiveTodo
+            | Action::EditTodo
+            | Action::MoveTodoUp
+            | Action::MoveTodoDown
+            | Action::Snooze
+            | Action::CycleStatus
+            | Action::SetStatus
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::SetPriority => BindingContext::Todo,
+            _ => BindingContext::Global,
+        }
+    }
+
+    // Whether it can change the data file, the others only move around or change the view.
+    // Custom commands say for themselves once they finish.
+    pub fn changes(self) -> bool {
+        !matches!(
+            self,
+            Action::ToggleGroup
+                | Action::CursorUp
+                | Action::CursorDown
+                | Action::GroupUp
+                | Action::GroupDown
+                | Action::HierarchyUp
+                | Action::HierarchyDown
+                | Action::CursorLeft
+                | Action::CursorRight
+                | Action::Quit
+                | Action::QuitWithoutSaving
+                | Action::Save
+                | Action::Help
+                | Action::CommandPalette
+                | Action::Activate
+                | Action::ToggleDebug
+                | Action::FilterStatus
+                | Action::TreeView
+                | Action::BoardView
+                | Action::CalendarView
+                | Action::AgendaView
+                | Action::ZoomIn
+                | Action::ZoomOut
+                | Action::Custom(_)
+        )
+    }
+}
+
+// Group or Todo depending on what the cursor is on
+pub fn cursor_context(config: &TodoConfig, cursor: &Cursor, settings: &Settings) -> BindingContext {
+    let Some(h) = cursor.position(config, settings) else {
+        return BindingContext::Global;
+    };
+    match h.find_item(config).map(|i| i.item) {
+        Ok(HierarchyItemEnum::Group(_)) => BindingContext::Group,
+        Ok(HierarchyItemEnum::Todo(_)) => BindingContext::Todo,
+        Err(_) => BindingContext::Global,
+    }
+}
+
+pub fn dispatch(
+    action: Action,
+    config: &mut TodoConfig,
+    cursor: &mut Cursor,
+    input: &mut dyn Input,
+    settings: &Settings,
+) -> Result<Outcome> {
+    // Actions for one kind of item do nothing on the other
+    let context = action.context();
+    if context != BindingContext::Global && context != cursor_context(config, cursor, settings) {
+        return Ok(Outcome::Done);
+    }
+
+    // Toggling the todo under the cursor changes the data, opening a group only changes the view
+    let changes = match action {
+        Action::Activate => cursor_context(config, cursor, settings) == BindingContext::Todo,
+        _ => action.changes(),
+    };
+
+    match action {
+        Action::Quit => return Ok(Outcome::Quit),
+        Action::QuitWithoutSaving => return Ok(Outcome::QuitWithoutSaving),
+        Action::Save => return Ok(Outcome::Save),
+        Action::Help => return Ok(Outcome::ShowHelp),
+        Action::CommandPalette => return Ok(Outcome::ShowPalette),
+        Action::ToggleDebug => return Ok(Outcome::ToggleDebug),
+        Action::Custom(i) => return Ok(Outcome::RunCustom(i)),
+        Action::AddTopGroup => {
+            let group_name = input.text("Group: ", "")?;
+            config.groups.push(Group {
+                id: new_id(),
+                name: group_name,
+                hidden: false,
+                due: None,
+                open: true,
+                todos: vec![],
+                completed: vec![],
+                todo_archive: vec![],
+                subgroups: vec![],
+                subgroup_archive: vec![],
+            });
+            return Ok(Outcome::Changed);
+        }
+        Action::Clean => {
+            clean(config);
+            return Ok(Outcome::Changed);
+        }
+        Action::FilterStatus => {
+            let answer = input.text("Show statuses (comma separated, empty for the tree): ", "")?;
+            let statuses = answer
+                .split(',')
+                .filter(|name| !name.trim().is_empty())
+                .map(|name| Ok(workflow::find(name, &settings.statuses)?.name.clone()))
+                .collect::<Result<Vec<_>>>()?;
+
+            let position = cursor.position(config, settings);
+            *cursor = if statuses.is_empty() {
+                Cursor::Hierarchy(position.unwrap_or_default())
+            } else {
+                let selected = position
+                    .and_then(|h| h.find_item(config).ok())
+                    .map(|i| i.item.id());
+                Cursor::List(ListView::new(statuses, selected))
+            };
+            return Ok(Outcome::Done);
+        }
+        Action::BoardView => {
+            let position = cursor.position(config, settings).unwrap_or_default();
+            *cursor = Cursor::Board(
+                BoardView::at(config, settings, &position)
+                    .ok_or_else(|| anyhow!("Nothing to show on a board"))?,
+            );
+            return Ok(Outcome::Done);
+        }
+        Action::CalendarView => {
+            *cursor = match cursor {
+                Cursor::Calendar(calendar) => Cursor::Calendar(CalendarView::new(
+                    calendar.day,
+                    !calendar.week,
+                    calendar.selected,
+                )),
+                _ => {
+                    let position = cursor.position(config, settings).unwrap_or_default();
+                    Cursor::Calendar(CalendarView::at(config, &position, false))
+                }
+            };
+            return Ok(Outcome::Done);
+        }
+        Action::AgendaView => {
+            let selected = cursor
+                .position(config, settings)
+                .and_then(|h| h.find_item(config).ok())
+                .map(|i| i.item.id());
+            *cursor = Cursor::Agenda(AgendaView::new(selected));
+            return Ok(Outcome::Done);
+        }
+        Action::ZoomIn => {
+            let mut position = cursor.position(config, settings).unwrap_or_default();
+            position.zoom_in(config)?;
+            *cursor = Cursor::Hierarchy(position);
+            return Ok(Outcome::Done);
+        }
+        Action::TreeView => {
+            let position = cursor.position(config, settings).unwrap_or_default();
+            position.reveal(config);
+            *cursor = Cursor::Hierarchy(position);
+            return Ok(Outcome::Done);
+        }
+        _ => {}
+    }
+
+    match cursor {
+        Cursor::Hierarchy(h) => dispatch_hierarchy(action, config, h, input, settings)?,
+        Cursor::List(list) => match action {
+            Action::CursorUp => list.cursor_up(config, settings),
+            Action::CursorDown => list.cursor_down(config, settings),
+            Action::GroupUp => list.group_up(config, settings),
+            Action::GroupDown => list.group_down(config, settings),
+            _ => {
+                let entries = list.entries(config, settings);
+                let Some(index) = list.index(&entries) else {
+                    return Ok(Outcome::Done);
+                };
+                list.selected = Some(entries[index].1.id);
+                let mut h = list.position(config, settings).unwrap_or_default();
+
+                dispatch_hierarchy(action, config, &mut h, input, settings)?;
+
+                // Something that takes the todo out of the list leaves the cursor on the row it was on
+                if !list
+                    .entries(config, settings)
+                    .iter()
+                    .any(|(_, t)| Some(t.id) == list.selected)
+                {
+                    list.select_index(config, settings, index);
+                }
+            }
+        },
+        Cursor::Board(board) => match action {
+            Action::CursorUp => board.cursor_up(config, settings),
+            Action::CursorDown => board.cursor_down(config, settings),
+            Action::CursorLeft => board.cursor_left(config, settings),
+            Action::CursorRight => board.cursor_right(config, settings),
+            _ => {
+                // Only cards are acted on, the root group stays put
+                let cards = board.cards(config, settings);
+                let Some(index) = board.index(&cards) else {
+                    return Ok(Outcome::Done);
+                };
+                board.selected = Some(cards[board.column][index].id);
+                let mut h = board.position(config, settings).unwrap_or_default();
+
+                dispatch_hierarchy(action, config, &mut h, input, settings)?;
+
+                board.reselect(config, settings, index);
+            }
+        },
+        Cursor::Calendar(calendar) => match action {
+            Action::CursorUp => calendar.cursor_up(config),
+            Action::CursorDown => calendar.cursor_down(config),
+            Action::CursorLeft => calendar.move_day(config, -1),
+            Action::CursorRight => calendar.move_day(config, 1),
+            Action::GroupUp => calendar.move_day(config, -7),
+            Action::GroupDown => calendar.move_day(config, 7),
+            Action::HierarchyUp => calendar.move_page(config, false),
+            Action::HierarchyDown => calendar.move_page(config, true),
+            Action::MoveLeft => calendar.reschedule(config, -1)?,
+            Action::MoveRight => calendar.reschedule(config, 1)?,
+            Action::MoveTodoUp => calendar.reschedule(config, -7)?,
+            Action::MoveTodoDown => calendar.reschedule(config, 7)?,
+            _ => {
+                let todos = CalendarView::todos_on(config, calendar.day);
+                let Some(index) = calendar.index(&todos) else {
+                    return Ok(Outcome::Done);
+                };
+                calendar.selected = Some(todos[index].1.id);
+                let mut h = calendar.position(config).unwrap_or_default();
+
+                dispatch_hierarchy(action, config, &mut h, input, settings)?;
+
+                // A new due date takes the todo off the day, the cursor stays on the row
+                if !CalendarView::todos_on(config, calendar.day)
+                    .iter()
+                    .any(|(_, t)| Some(t.id) == calendar.selected)
+                {
+                    calendar.select(config, index);
+                }
+            }
+        },
+        Cursor::Agenda(agenda) => match action {
+            Action::CursorUp => agenda.cursor_up(config),
+            Action::CursorDown => agenda.cursor_down(config),
+            Action::GroupUp => agenda.section_up(config),
+            Action::GroupDown => agenda.section_down(config),
+            _ => {
+                let entries = AgendaView::entries(config);
+                let Some(index) = agenda.index(&entries) else {
+                    return Ok(Outcome::Done);
+                };
+                agenda.selected = Some(entries[index].2.id);
+                let mut h = agenda.position(config).unwrap_or_default();
+
+                dispatch_hierarchy(action, config, &mut h, input, settings)?;
+
+                // Finished todos leave the agenda, the cursor stays on the row
+                if !AgendaView::entries(config)
+                    .iter()
+                    .any(|(_, _, t)| Some(t.id) == agenda.selected)
+                {
+                    agenda.select(config, index);
+                }
+            }
+        },
+    }
+
+    Ok(if changes {
+        Outcome::Changed
+    } else {
+        Outcome::Done
+    })
+}
+
+fn dispatch_hierarchy(
+    action: Action,
+    config: &mut TodoConfig,
+    h: &mut PositionHierarchy,
+    input: &mut dyn Input,
+    settings: &Settings,
+) -> Result<()> {
+    match action {
+        Action::CursorUp => {
+            h.cursor_up(config).ok();
+        }
+        Action::CursorDown => {
+            h.cursor_down(config).ok();
+        }
+        Action::GroupUp => {
+            h.group_up(config).ok();
+        }
+        Action::GroupDown => {
+            h.group_down(config).ok();
+        }
+        Action::HierarchyUp => {
+            h.hierarchy_up(config).ok();
+        }
+        Action::HierarchyDown => {
+            h.hierarchy_down(config).ok();
+        }
+        Action::ToggleGroup => {
+            if let HierarchyItemEnumMut::Group(g) = h.find_item_mut(config)?.item {
+                g.open = !g.open;
+            }
+        }
+        Action::ToggleTodo => toggle_todo(config, h)?,
+        Action::Activate => match h.find_item(config)?.item {
+            HierarchyItemEnum::Group(_) => {
+                if let HierarchyItemEnumMut::Group(g) = h.find_item_mut(config)?.item {
+                    g.open = !g.open;
+                }
+            }
+            HierarchyItemEnum::Todo(_) => toggle_todo(config, h)?,
+        },
+        Action::ArchiveTodo => archive_todo(config, h)?,
+        Action::HideGroup => {
+            // The zoomed in group is hidden from the group above it
+            if h.indexes.len() == 1 && !h.root.is_empty() {
+                h.zoom_out();
+            }
+            if h.indexes.len() == 1 {
+                let t = config.groups.remove(h.last()?);
+                config.archive_groups.push(t);
+
+                if h.last()? >= config.groups.len() && h.last()? > 0 {
+                    *h.last_mut()? -= 1;
+                }
+            } else {
+                let g = h.find_group_mut(config)?;
+                let t = g.subgroups.remove(h.last()?);
+                g.subgroup_archive.push(t);
+                if h.last()? >= g.len() {
+                    if h.last()? > 0 {
+                        *h.last_mut()? -= 1;
+                    } else {
+                        h.hierarchy_up(config)?;
+                    }
+                }
+            }
+        }
+        Action::AddTodo => {
+            if let HierarchyItemEnumMut::Group(g) = h.find_item_mut(config)?.item {
+                let todo_name = input.text("Todo: ", "")?;
+                g.todos.push(Todo {
+                    id: new_id(),
+                    name: todo_name,
+                    done_time: None,
+                    due: prompt_date(input).or(g.due), // Without one of its own it's due with the group
+                    defer_until: None,
+                    status: String::new(),
+                    priority: Priority::Normal,
+                    created: now(),
+                });
+            }
+        }
+        Action::EditTodo => {
+            if let HierarchyItemEnumMut::Todo(t) = h.find_item_mut(config)?.item {
+                let todo_name = input.text("Todo: ", &t.name)?;
+                if !todo_name.is_empty() {
+                    t.name = todo_name;
+                }
+
+                if let Some(due) = t.due {
+                    t.due = Some(prompt_date_in_place(input, due).unwrap_or(due));
+                } else {
+                    t.due = prompt_date(input);
+                }
+            }
+        }
+        Action::AddGroup => {
+            if let HierarchyItemEnumMut::Group(g) = h.find_item_mut(config)?.item {
+                let group_name = input.text("Group: ", "")?;
+                g.subgroups.push(Group {
+                    id: new_id(),
+                    name: group_name,
+                    hidden: false,
+                    due: None,
+                    open: true,
+                    todos: vec![],
+                    completed: vec![],
+                    todo_archive: vec![],
+                    subgroups: vec![],
+                    subgroup_archive: vec![],
+                });
+            }
+        }
+        Action::EditGroup => {
+            if let HierarchyItemEnumMut::Group(g) = h.find_item_mut(config)?.item {
+                let group_name = input.text("Group: ", &format!("{} ", &g.name))?;
+                if !group_name.is_empty() {
+                    g.name = group_name;
+                }
+            }
+        }
+        // The zoomed in group has nothing beside it to swap with
+        Action::MoveGroupDown | Action::MoveGroupUp
+            if h.indexes.len() == 1 && !h.root.is_empty() => {}
+        Action::MoveGroupDown => {
+            let group = h.find_group_mut(config)?;
+            if h.last()? + 1 < group.subgroups.len() {
+                group.subgroups.swap(h.last()?, h.last()? + 1);
+                *h.last_mut()? += 1;
+            }
+        }
+        Action::MoveGroupUp => {
+            let group = h.find_group_mut(config)?;
+            if h.last()? > 0 {
+                group.subgroups.swap(h.last()?, h.last()? - 1);
+                *h.last_mut()? -= 1;
+            }
+        }
+        Action::MoveTodoDown => {
+            let group = h.find_group_mut(config)?;
+            if h.last()? + 1 < group.subgroups.len() + group.todos.len()
+                && h.last()? >= group.subgroups.len()
+            {
+                group.todos.swap(
+                    h.last()? - group.subgroups.len(),
+                    h.last()? + 1 - group.subgroups.len(),
+                );
+                *h.last_mut()? += 1;
+            }
+        }
+        Action::MoveTodoUp => {
+            let group = h.find_group_mut(config)?;
+            if h.last()? > group.subgroups.len()
+                && h.last()? < group.subgroups.len() + group.todos.len()
+            {
+                group.todos.swap(
+                    h.last()? - group.subgroups.len(),
+                    h.last()? - 1 - group.subgroups.len(),
+                );
+                *h.last_mut()? -= 1;
+            }
+        }
+        Action::MoveTo => move_to(config, h, input)?,
+        Action::SetDue => {
+            let due = match h.find_item_mut(config)?.item {
+                HierarchyItemEnumMut::Todo(t) => &mut t.due,
+                HierarchyItemEnumMut::Group(g) => &mut g.due,
+            };
+            let format = format_description::parse("[year]-[month]-[day] [hour]:[minute]")?;
+            let current = match due {
+                Some(due) => due.format(&format)?,
+                None => String::new(),
+            };
+
+            let answer = input.text("Due (empty for none): ", &current)?;
+            *due = if answer.trim().is_empty() || answer.trim() == "none" {
+                None
+            } else {
+                let now = now();
+                Some(
+                    parse_date(&answer, now)
+                        .ok_or_else(|| anyhow!("Can't read \"{}\" as a date", answer))?,
+                )
+            };
+        }
+        Action::Snooze => {
+            if let HierarchyItemEnumMut::Todo(t) = h.find_item_mut(config)?.item {
+                let answer = input.text("Snooze until (empty to wake it): ", "tomorrow")?;
+                t.defer_until = if answer.trim().is_empty() {
+                    None
+                } else {
+                    let now = now();
+                    Some(
+                        parse_start(&answer, now)
+                            .ok_or_else(|| anyhow!("Can't read \"{}\" as a date", answer))?,
+                    )
+                };
+            }
+        }
+        Action::CycleStatus => {
+            if let HierarchyItemEnum::Todo(t) = h.find_item(config)?.item {
+                if let Some(status) = workflow::next(t, &settings.statuses) {
+                    workflow::set_status(config, h, &status.clone())?;
+                }
+            }
+        }
+        Action::SetStatus => {
+            if let HierarchyItemEnum::Todo(t) = h.find_item(config)?.item {
+                let current = workflow::status_of(t, &settings.statuses)
+                    .map(|s| s.name.clone())
+                    .unwrap_or_default();
+                let answer = input.text("Status: ", &current)?;
+                let status = workflow::find(&answer, &settings.statuses)?;
+                workflow::set_status(config, h, status)?;
+            }
+        }
+        Action::MoveLeft | Action::MoveRight => {
+            if let HierarchyItemEnum::Todo(t) = h.find_item(config)?.item {
+                let columns = board_view::columns(settings);
+                let current = workflow::status_of(t, &settings.statuses)
+                    .and_then(|status| columns.iter().position(|s| *s == status));
+                let next = match (current, action) {
+                    (Some(i), Action::MoveLeft) => i.checked_sub(1),
+                    (Some(i), _) => Some(i + 1),
+                    (None, _) => None,
+                };
+                if let Some(status) = next.and_then(|i| columns.get(i)) {
+                    workflow::set_status(config, h, &(*status).clone())?;
+                }
+            }
+        }
+        Action::SetPriority => {
+            if let HierarchyItemEnumMut::Todo(t) = h.find_item_mut(config)?.item {
+                let current = format!("{:?}", t.priority).to_lowercase();
+                let answer = input.text("Priority (low, normal, high): ", &current)?;
+                t.priority = Priority::parse(&answer)
+                    .ok_or_else(|| anyhow!("No priority \"{}\"", answer.trim()))?;
+            }
+        }
+        Action::ZoomOut => h.zoom_out(),
+        Action::AddTopGroup
+        | Action::ZoomIn
+        | Action::CommandPalette
+        | Action::ToggleDebug
+        | Action::FilterStatus
+        | Action::TreeView
+        | Action::BoardView
+        | Action::CalendarView
+        | Action::AgendaView
+        | Action::CursorLeft
+        | Action::CursorRight
+        | Action::Custom(_)
+        | Action::Quit
+        | Action::QuitWithoutSaving
+        | Action::Save
+        | Action::Clean
+        | Action::Help => {}
+    }
+
+    Ok(())
+}
+
+fn move_to(
+    config: &mut TodoConfig,
+    h: &mut PositionHierarchy,
+    input: &mut dyn Input,
+) -> Result<()> {
+    let path = input.text("Move to: ", "")?;
+    let target = PositionHierarchy::find_group_path(config, &path)
+        .ok_or_else(|| anyhow!("No group at \"{}\"", path))?;
+    let target_id = target.find_item(config)?.item.id();
+
+    let moving_id = match h.find_item(config)?.item {
+        HierarchyItemEnum::Todo(t) => {
+            let id = t.id;
+            let g = h.find_group_mut(config)?;
+            let (todo, done) = if h.last()? < g.subgroups.len() + g.todos.len() {
+                (g.todos.remove(h.last()? - g.subgroups.len()), false)
+            } else {
+                let i = h.last()? - g.subgroups.len() - g.todos.len();
+                (g.completed.remove(i), true)
+            };
+
+            if let HierarchyItemEnumMut::Group(g) = target.find_item_mut(config)?.item {
+                if done {
+                    g.completed.push(todo);
+                } else {
+                    g.todos.push(todo);
+                }
+            }
+            id
+        }
+        HierarchyItemEnum::Group(g) => {
+            let id = g.id;
+            if g.id == target_id || PositionHierarchy::find_id_in(g, target_id) {
+                return Err(anyhow!("Can't move a group into itself"));
+            }
+
+            if h.indexes.len() == 1 && !h.root.is_empty() {
+                h.zoom_out();
+            }
+            let group = if h.indexes.len() == 1 {
+                config.groups.remove(h.last()?)
+            } else {
+                h.find_group_mut(config)?.subgroups.remove(h.last()?)
+            };
+
+            // Removing the group can shift the target, find it again
+            let target = PositionHierarchy::find_id(config, target_id)
+                .ok_or_else(|| anyhow!("No group at \"{}\"", path))?;
+            if let HierarchyItemEnumMut::Group(g) = target.find_item_mut(config)?.item {
+                g.subgroups.push(group);
+            }
+            id
+        }
+    };
+
+    h.refind(config, moving_id);
+    h.reveal(config);
+
+    Ok(())
+}
+
+pub fn archive_todo(config: &mut TodoConfig, h: &mut PositionHierarchy) -> Result<()> {
+    let g = h.find_group_mut(config)?;
+    let t = if h.last()? < g.subgroups.len() + g.todos.len() {
+        g.todos.remove(h.last()? - g.subgroups.len())
+    } else {
+        g.completed
+            .remove(h.last()? - g.subgroups.len() - g.todos.len())
+    };
+    g.todo_archive.push(t);
+
+    if h.last()? >= g.len() {
+        if h.last()? > 0 {
+            *h.last_mut()? -= 1;
+        } else {
+            h.hierarchy_up(config)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn toggle_todo(config: &mut TodoConfig, h: &mut PositionHierarchy) -> Result<()> {
+    let g = h.find_group_mut(config)?;
+    if h.last()? < g.subgroups.len() + g.todos.len() {
+        let mut t = g.todos.remove(h.last()? - g.subgroups.len());
+        t.done_time = Some(now());
+        g.completed.push(t);
+    } else if h.last()? < g.subgroups.len() + g.todos.len() + g.completed.len() {
+        let mut t = g
+            .completed
+            .remove(h.last()? - g.subgroups.len() - g.todos.len());
+        t.done_time = None;
+        g.todos.push(t);
+    }
+
+    Ok(())
+}
+
+// Used when the last top level group is gone, the tree can't be empty
+pub fn create_top_group(config: &mut TodoConfig, input: &mut dyn Input) -> Result<()> {
+    let name = input.text("Enter Name for Top Group: ", "")?;
+
+    config.groups.push(Group {
+        id: new_id(),
+        hidden: false,
+        due: None,
+        name,
+        open: false,
+        todos: vec![],
+        completed: vec![],
+        todo_archive: vec![],
+        subgroups: vec![],
+        subgroup_archive: vec![],
+    });
+
+    Ok(())
+}
+
+// Empties every archive
+fn clean(config: &mut TodoConfig) {
+    config.archive_groups = vec![];
+    for group in config.groups.iter_mut() {
+        group.traverse_mut(
+            (),
+            |g, _d, v| {
+                g.todo_archive = vec![];
+                g.subgroup_archive = vec![];
+
+                (true, v)
+            },
+            |_t, _d, v| v,
+            |_g, _d, v| v,
+            0,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        keybindings::{Bindings, KeyPress, Keybindings},
+        prompt::ScriptedInput,
+    };
+
+    use super::*;
+
+    fn todo(name: &str) -> Todo {
+        Todo {
+            id: new_id(),
+            name: name.to_string(),
+            done_time: None,
+            due: None,
+            defer_until: None,
+            status: String::new(),
+            priority: Priority::Normal,
+            created: now(),
+        }
+    }
+
+    fn group(name: &str, todos: Vec<Todo>, subgroups: Vec<Group>) -> Group {
+        Group {
+            id: new_id(),
+            name: name.to_string(),
+            hidden: false,
+            due: None,
+            open: true,
+            todos,
+            completed: vec![],
+            todo_archive: vec![],
+            subgroups,
+            subgroup_archive: vec![],
+        }
+    }
+
+    // School
+    //   AP CSP
+    //     Computering
+    //   Read chapter 3
+    //   Essay
+    fn config() -> TodoConfig {
+        TodoConfig {
+            version: 0,
+            groups: vec![group(
+                "School",
+                vec![todo("Read chapter 3"), todo("Essay")],
+                vec![group("AP CSP", vec![todo("Computering")], vec![])],
+            )],
+            archive_groups: vec![],
+        }
+    }
+
+    // Runs the actions in order with the answers for their prompts
+    fn run(
+        config: &mut TodoConfig,
+        cursor: &mut Cursor,
+        actions: &[Action],
+        answers: &[&str],
+    ) -> Vec<Outcome> {
+        let settings = Settings::default();
+        let mut input = ScriptedInput::new(answers.iter().map(|a| a.to_string()));
+        actions
+            .iter()
+            .map(|action| dispatch(*action, config, cursor, &mut input, &settings).unwrap())
+            .collect()
+    }
+
+    fn selected_name(config: &TodoConfig, cursor: &Cursor) -> String {
+        let h = cursor.position(config, &Settings::default()).unwrap();
+        match h.find_item(config).unwrap().item {
+            HierarchyItemEnum::Group(g) => g.name.clone(),
+            HierarchyItemEnum::Todo(t) => t.name.clone(),
+        }
+    }
+
+    #[test]
+    fn toggle() {
+        let mut config = config();
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
+        // Down past AP CSP and Computering
+        run(
+            &mut config,
+            &mut cursor,
+            &[Action::CursorDown, Action::CursorDown, Action::CursorDown],
+            &[],
+        );
+        assert_eq!(selected_name(&config, &cursor), "Read chapter 3");
+
+        run(&mut config, &mut cursor, &[Action::ToggleTodo], &[]);
+        let school = &config.groups[0];
+        assert_eq!(school.todos.len(), 1);
+        assert_eq!(school.completed[0].name, "Read chapter 3");
+        assert!(school.completed[0].done_time.is_some());
+
+        // Toggling a group only opens and closes it
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
+        run(&mut config, &mut cursor, &[Action::ToggleTodo], &[]);
+        assert!(config.groups[0].open);
+        run(&mut config, &mut cursor, &[Action::ToggleGroup], &[]);
+        assert!(!config.groups[0].open);
+    }
+
+    #[test]
+    fn add_and_move() {
+        let mut config = config();
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
+        run(
+            &mut config,
+            &mut cursor,
+            &[Action::AddTodo],
+            &["Read chapter 4", "n"],
+        );
+        assert_eq!(config.groups[0].todos[2].name, "Read chapter 4");
+        assert_eq!(config.groups[0].todos[2].due, None);
+
+        cursor = Cursor::Hierarchy(PositionHierarchy {
+            indexes: vec![0, 3],
+            root: vec![],
+        });
+        assert_eq!(selected_name(&config, &cursor), "Read chapter 4");
+        run(
+            &mut config,
+            &mut cursor,
+            &[Action::MoveTo],
+            &["School/AP CSP"],
+        );
+        assert_eq!(config.groups[0].todos.len(), 2);
+        assert_eq!(
+            config.groups[0].subgroups[0].todos[1].name,
+            "Read chapter 4"
+        );
+        // The cursor goes along with it
+        assert_eq!(selected_name(&config, &cursor), "Read chapter 4");
+
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
+        let moved = dispatch(
+            Action::MoveTo,
+            &mut config,
+            &mut cursor,
+            &mut ScriptedInput::new(["School/AP CSP".to_string()]),
+            &Settings::default(),
+        );
+        assert!(moved.is_err(), "A group can't go inside itself");
+    }
+
+    #[test]
+    fn chord() {
+        let keybindings: Keybindings = ron::from_str(r#"(archive_todo: "d d")"#).unwrap();
+        let bindings = Bindings::new(&keybindings, &[]);
+        let mut config = config();
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy {
+            indexes: vec![0, 2],
+            root: vec![],
+        });
+        run(&mut config, &mut cursor, &[Action::ToggleTodo], &[]);
+
+        let d = KeyPress::parse("d").unwrap();
+        let mut pending = vec![];
+        let on = cursor_context(&config, &cursor, &Settings::default());
+        bindings.press(&mut pending, d, on);
+        bindings.press(&mut pending, d, on);
+        let action = bindings.resolve(&pending, on).unwrap();
+        assert_eq!(action, Action::ArchiveTodo);
+
+        run(&mut config, &mut cursor, &[action], &[]);
+        assert!(config.groups[0].completed.is_empty());
+        assert_eq!(config.groups[0].todo_archive[0].name, "Essay");
+    }
+
+    #[test]
+    fn outcomes() {
+        let mut config = config();
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
+        let outcomes = run(
+            &mut config,
+            &mut cursor,
+            &[Action::Save, Action::Help, Action::Quit, Action::Custom(2)],
+            &[],
+        );
+        assert_eq!(
+            outcomes,
+            [
+                Outcome::Save,
+                Outcome::ShowHelp,
+                Outcome::Quit,
+                Outcome::RunCustom(2)
+            ]
+        );
+
+        // Only changes to the data need saving
+        let outcomes = run(
+            &mut config,
+            &mut cursor,
+            &[Action::Activate, Action::CursorUp, Action::AddTodo],
+            &["Flashcards"],
+        );
+        assert_eq!(outcomes, [Outcome::Done, Outcome::Done, Outcome::Changed]);
+    }
+
+    #[test]
+    fn zoom() {
+        let mut config = config();
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
+        run(
+            &mut config,
+            &mut cursor,
+            &[Action::CursorDown, Action::ZoomIn, Action::CursorDown],
+            &[],
+        );
+        assert_eq!(selected_name(&config, &cursor), "Computering");
+        // At the bottom of the zoomed in group
+        run(&mut config, &mut cursor, &[Action::CursorDown], &[]);
+        assert_eq!(selected_name(&config, &cursor), "Computering");
+
+        // Out a level at a time
+        run(&mut config, &mut cursor, &[Action::ZoomOut], &[]);
+        assert_eq!(selected_name(&config, &cursor), "Computering");
+        let Cursor::Hierarchy(h) = &cursor else {
+            panic!("Zooming stays in the tree");
+        };
+        assert_eq!(
+            (h.root.as_slice(), h.indexes.as_slice()),
+            (&[0][..], &[0, 0, 0][..])
+        );
+
+        run(&mut config, &mut cursor, &[Action::ZoomOut], &[]);
+        let Cursor::Hierarchy(h) = &cursor else {
+            panic!("Zooming stays in the tree");
+        };
+        assert!(h.root.is_empty());
+        assert_eq!(h.indexes, [0, 0, 0]);
+        assert_eq!(selected_name(&config, &cursor), "Computering");
+    }
+}
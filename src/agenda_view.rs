@@ -0,0 +1,124 @@
+use std::fmt::Display;
+
+use time::OffsetDateTime;
+
+use crate::{
+    calendar_view::day_of,
+    dates::now,
+    navigation::PositionHierarchy,
+    selection,
+    todo_config::{Priority, Todo, TodoConfig},
+};
+
+// What is left to do soon, in sections by when it is due
+pub struct AgendaView {
+    pub selected: Option<u64>, // Id of the todo under the cursor, the first one if it's gone
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section {
+    Overdue,
+    Today,
+    Tomorrow,
+    NextWeek,     // The 7 days after tomorrow
+    HighPriority, // Without a due date
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Section::Overdue => write!(f, "Overdue"),
+            Section::Today => write!(f, "Today"),
+            Section::Tomorrow => write!(f, "Tomorrow"),
+            Section::NextWeek => write!(f, "Next 7 days"),
+            Section::HighPriority => write!(f, "High priority"),
+        }
+    }
+}
+
+impl Section {
+    // Open todos that aren't snoozed, and where they go
+    fn of(todo: &Todo, now: OffsetDateTime) -> Option<Self> {
+        if todo.done_time.is_some() || todo.is_deferred(now) {
+            return None;
+        }
+        let Some(due) = todo.due else {
+            return (todo.priority == Priority::High).then_some(Section::HighPriority);
+        };
+
+        let days = (day_of(due, now) - now.date()).whole_days();
+        if due < now {
+            Some(Section::Overdue)
+        } else if days <= 0 {
+            Some(Section::Today)
+        } else if days == 1 {
+            Some(Section::Tomorrow)
+        } else if days <= 8 {
+            Some(Section::NextWeek)
+        } else {
+            None
+        }
+    }
+}
+
+impl AgendaView {
+    pub fn new(selected: Option<u64>) -> Self {
+        Self { selected }
+    }
+
+    // Section, group path and todo for everything listed. Sections are in order, soonest due
+    // first within them.
+    pub fn entries(config: &TodoConfig) -> Vec<(Section, Vec<&str>, &Todo)> {
+        let now = now();
+        let mut entries: Vec<_> = config
+            .todos()
+            .into_iter()
+            .filter_map(|(path, todo)| Some((Section::of(todo, now)?, path, todo)))
+            .collect();
+        // Stable, so todos due at the same time stay in tree order
+        entries.sort_by_key(|(section, _, todo)| (*section, todo.due));
+        entries
+    }
+
+    pub fn index(&self, entries: &[(Section, Vec<&str>, &Todo)]) -> Option<usize> {
+        selection::index(self.selected, &ids(entries))
+    }
+
+    pub fn position(&self, config: &TodoConfig) -> Option<PositionHierarchy> {
+        let entries = Self::entries(config);
+        let (_, _, todo) = entries.get(self.index(&entries)?)?;
+        PositionHierarchy::find_id(config, todo.id)
+    }
+
+    pub fn select(&mut self, config: &TodoConfig, index: usize) {
+        selection::select(&mut self.selected, &ids(&Self::entries(config)), index);
+    }
+
+    pub fn cursor_up(&mut self, config: &TodoConfig) {
+        selection::up(&mut self.selected, &ids(&Self::entries(config)));
+    }
+
+    pub fn cursor_down(&mut self, config: &TodoConfig) {
+        selection::down(&mut self.selected, &ids(&Self::entries(config)));
+    }
+
+    // To the first todo of the section before
+    pub fn section_up(&mut self, config: &TodoConfig) {
+        let entries = Self::entries(config);
+        selection::run_up(&mut self.selected, &ids(&entries), |i| {
+            entries[i - 1].0 != entries[i].0
+        });
+    }
+
+    // To the first todo of the section after
+    pub fn section_down(&mut self, config: &TodoConfig) {
+        let entries = Self::entries(config);
+        selection::run_down(&mut self.selected, &ids(&entries), |i| {
+            entries[i - 1].0 != entries[i].0
+        });
+    }
+}
+
+fn ids(entries: &[(Section, Vec<&str>, &Todo)]) -> Vec<u64> {
+    entries.iter().map(|(_, _, t)| t.id).collect()
+}
@@ -0,0 +1,146 @@
+use crate::{
+    navigation::{HierarchyItemEnum, PositionHierarchy},
+    selection,
+    settings::Settings,
+    todo_config::{Group, Todo, TodoConfig},
+    workflow::{status_of, Status, StatusKind},
+};
+
+// The todos below a group as cards in a column for each status
+pub struct BoardView {
+    pub root: u64,             // Id of the group whose subtree is on the board
+    pub column: usize,         // Column the cursor is in
+    pub selected: Option<u64>, // Id of the card under the cursor, the column's first if it's gone
+}
+
+impl BoardView {
+    pub fn new(root: u64, column: usize, selected: Option<u64>) -> Self {
+        Self {
+            root,
+            column,
+            selected,
+        }
+    }
+
+    // Opens on the group under the position, or the one the todo there is in
+    pub fn at(config: &TodoConfig, settings: &Settings, h: &PositionHierarchy) -> Option<Self> {
+        let item = h.find_item(config).ok()?;
+        let (root, selected) = match item.item {
+            HierarchyItemEnum::Group(g) => (g.id, None),
+            HierarchyItemEnum::Todo(t) => (h.find_group(config).ok()?.id, Some(t)),
+        };
+        let column = selected
+            .and_then(|t| status_of(t, &settings.statuses))
+            .and_then(|status| columns(settings).iter().position(|s| *s == status))
+            .unwrap_or(0);
+        Some(Self::new(root, column, selected.map(|t| t.id)))
+    }
+
+    pub fn root_group<'a>(&self, config: &'a TodoConfig) -> Option<&'a Group> {
+        let h = PositionHierarchy::find_id(config, self.root)?;
+        match h.find_item(config).ok()?.item {
+            HierarchyItemEnum::Group(g) => Some(g),
+            HierarchyItemEnum::Todo(_) => None,
+        }
+    }
+
+    // The cards of each column, in tree order
+    pub fn cards<'a>(&self, config: &'a TodoConfig, settings: &Settings) -> Vec<Vec<&'a Todo>> {
+        let columns = columns(settings);
+        let mut cards = vec![vec![]; columns.len()];
+        let Some(root) = self.root_group(config) else {
+            return cards;
+        };
+        for (_, todo) in root.all_todos() {
+            let column = status_of(todo, &settings.statuses)
+                .and_then(|status| columns.iter().position(|s| *s == status));
+            if let Some(column) = column {
+                cards[column].push(todo);
+            }
+        }
+        cards
+    }
+
+    pub fn index(&self, cards: &[Vec<&Todo>]) -> Option<usize> {
+        selection::index(self.selected, &ids(cards.get(self.column)?))
+    }
+
+    // The card under the cursor, or the root group when the column is empty
+    pub fn position(&self, config: &TodoConfig, settings: &Settings) -> Option<PositionHierarchy> {
+        let cards = self.cards(config, settings);
+        let id = match self.index(&cards) {
+            Some(i) => cards[self.column][i].id,
+            None => self.root,
+        };
+        PositionHierarchy::find_id(config, id)
+    }
+
+    // Selects the card at index in the column, or the last one when there are fewer
+    pub fn select(
+        &mut self,
+        config: &TodoConfig,
+        settings: &Settings,
+        column: usize,
+        index: usize,
+    ) {
+        let cards = self.cards(config, settings);
+        self.column = column.min(cards.len().saturating_sub(1));
+        let ids = cards
+            .get(self.column)
+            .map_or_else(Vec::new, |cards| ids(cards));
+        selection::select(&mut self.selected, &ids, index);
+    }
+
+    // Follows the selected card to whatever column it is in now, or stays on the same row
+    pub fn reselect(&mut self, config: &TodoConfig, settings: &Settings, index: usize) {
+        let cards = self.cards(config, settings);
+        let found = cards.iter().enumerate().find_map(|(column, cards)| {
+            cards
+                .iter()
+                .any(|t| Some(t.id) == self.selected)
+                .then_some(column)
+        });
+        match found {
+            Some(column) => self.column = column,
+            None => self.select(config, settings, self.column, index),
+        }
+    }
+
+    pub fn cursor_up(&mut self, config: &TodoConfig, settings: &Settings) {
+        if let Some(cards) = self.cards(config, settings).get(self.column) {
+            selection::up(&mut self.selected, &ids(cards));
+        }
+    }
+
+    pub fn cursor_down(&mut self, config: &TodoConfig, settings: &Settings) {
+        if let Some(cards) = self.cards(config, settings).get(self.column) {
+            selection::down(&mut self.selected, &ids(cards));
+        }
+    }
+
+    // To the same row of the next column over, or as near it as that column goes
+    pub fn cursor_left(&mut self, config: &TodoConfig, settings: &Settings) {
+        let cards = self.cards(config, settings);
+        let index = self.index(&cards).unwrap_or(0);
+        self.select(config, settings, self.column.saturating_sub(1), index);
+    }
+
+    pub fn cursor_right(&mut self, config: &TodoConfig, settings: &Settings) {
+        let cards = self.cards(config, settings);
+        let index = self.index(&cards).unwrap_or(0);
+        self.select(config, settings, self.column + 1, index);
+    }
+}
+
+fn ids(cards: &[&Todo]) -> Vec<u64> {
+    cards.iter().map(|t| t.id).collect()
+}
+
+// Cancelled todos are in the archive, so they don't get a column
+pub fn columns(settings: &Settings) -> Vec<&Status> {
+    settings
+        .statuses
+        .iter()
+        .filter(|s| s.kind != StatusKind::Cancelled)
+        .collect()
+}
@@ -0,0 +1,202 @@
+use time::{Date, Duration, OffsetDateTime};
+
+use crate::{
+    dates::now,
+    navigation::{HierarchyItemEnum, HierarchyItemEnumMut, PositionHierarchy},
+    selection,
+    todo_config::{Todo, TodoConfig},
+};
+
+// Todos placed on the days they are due, a month or a week at a time
+pub struct CalendarView {
+    pub day: Date,             // The picked day, its todos are listed
+    pub week: bool,            // Just the week the day is in, with room for names
+    pub selected: Option<u64>, // Id of the todo under the cursor, the day's first if it's gone
+}
+
+impl CalendarView {
+    pub fn new(day: Date, week: bool, selected: Option<u64>) -> Self {
+        Self {
+            day,
+            week,
+            selected,
+        }
+    }
+
+    // Opens on the day the todo under the position is due, today otherwise
+    pub fn at(config: &TodoConfig, h: &PositionHierarchy, week: bool) -> Self {
+        let now = now();
+        match h.find_item(config).map(|i| i.item) {
+            Ok(HierarchyItemEnum::Todo(t)) if t.due.is_some() => Self::new(
+                t.due.map_or(now.date(), |due| day_of(due, now)),
+                week,
+                Some(t.id),
+            ),
+            _ => Self::new(now.date(), week, None),
+        }
+    }
+
+    // The todos due on the day, in tree order
+    pub fn todos_on(config: &TodoConfig, day: Date) -> Vec<(Vec<&str>, &Todo)> {
+        let now = now();
+        config
+            .todos()
+            .into_iter()
+            .filter(|(_, t)| t.due.is_some_and(|due| day_of(due, now) == day))
+            .collect()
+    }
+
+    pub fn index(&self, todos: &[(Vec<&str>, &Todo)]) -> Option<usize> {
+        selection::index(self.selected, &ids(todos))
+    }
+
+    pub fn position(&self, config: &TodoConfig) -> Option<PositionHierarchy> {
+        let todos = Self::todos_on(config, self.day);
+        let (_, todo) = todos.get(self.index(&todos)?)?;
+        PositionHierarchy::find_id(config, todo.id)
+    }
+
+    pub fn select(&mut self, config: &TodoConfig, index: usize) {
+        selection::select(
+            &mut self.selected,
+            &ids(&Self::todos_on(config, self.day)),
+            index,
+        );
+    }
+
+    pub fn cursor_up(&mut self, config: &TodoConfig) {
+        selection::up(&mut self.selected, &ids(&Self::todos_on(config, self.day)));
+    }
+
+    pub fn cursor_down(&mut self, config: &TodoConfig) {
+        selection::down(&mut self.selected, &ids(&Self::todos_on(config, self.day)));
+    }
+
+    // Picks another day, starting from its first todo. Stays put at the ends of the calendar.
+    pub fn move_day(&mut self, config: &TodoConfig, days: i64) {
+        if let Some(day) = self.day.checked_add(Duration::days(days)) {
+            self.day = day;
+            self.select(config, 0);
+        }
+    }
+
+    // A month back or on, or a week in the week view
+    pub fn move_page(&mut self, config: &TodoConfig, forward: bool) {
+        if self.week {
+            self.move_day(config, if forward { 7 } else { -7 });
+            return;
+        }
+
+        let (year, month) = if forward {
+            let month = self.day.month().next();
+            let year = self.day.year() + (month == time::Month::January) as i32;
+            (year, month)
+        } else {
+            let month = self.day.month().previous();
+            let year = self.day.year() - (month == time::Month::December) as i32;
+            (year, month)
+        };
+        // The 31st becomes the last day of a shorter month
+        let day = self
+            .day
+            .day()
+            .min(time::util::days_in_year_month(year, month));
+        if let Ok(date) = Date::from_calendar_date(year, month, day) {
+            self.day = date;
+            self.select(config, 0);
+        }
+    }
+
+    // Moves the selected todo's due date by a number of days, keeping the time, and the view
+    // along with it
+    pub fn reschedule(&mut self, config: &mut TodoConfig, days: i64) -> anyhow::Result<()> {
+        let Some(h) = self.position(config) else {
+            return Ok(());
+        };
+        if let HierarchyItemEnumMut::Todo(t) = h.find_item_mut(config)?.item {
+            if let Some(due) = &mut t.due {
+                *due = due
+                    .checked_add(Duration::days(days))
+                    .ok_or_else(|| anyhow::anyhow!("Can't move it past the end of the calendar"))?;
+                self.day = day_of(*due, now());
+                self.selected = Some(t.id);
+            }
+        }
+        Ok(())
+    }
+
+    // The days shown, from the Monday of the first week to the Sunday of the last
+    pub fn days(&self) -> Vec<Date> {
+        let (first, last) = if self.week {
+            (self.day, self.day)
+        } else {
+            let first = self.day.replace_day(1).unwrap_or(self.day);
+            let days = time::util::days_in_year_month(self.day.year(), self.day.month());
+            let last = self.day.replace_day(days).unwrap_or(self.day);
+            (first, last)
+        };
+        // Cut short at the ends of the calendar
+        let start = first
+            .checked_sub(Duration::days(
+                first.weekday().number_days_from_monday() as i64
+            ))
+            .unwrap_or(Date::MIN);
+        let end = last
+            .checked_add(Duration::days(
+                6 - last.weekday().number_days_from_monday() as i64,
+            ))
+            .unwrap_or(Date::MAX);
+
+        let mut days = vec![];
+        let mut day = Some(start);
+        while let Some(d) = day.filter(|d| *d <= end) {
+            days.push(d);
+            day = d.next_day();
+        }
+        days
+    }
+}
+
+fn ids(todos: &[(Vec<&str>, &Todo)]) -> Vec<u64> {
+    todos.iter().map(|(_, t)| t.id).collect()
+}
+
+// The local day a date falls on
+pub fn day_of(date: OffsetDateTime, now: OffsetDateTime) -> Date {
+    date.to_offset(now.offset()).date()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo_config::new_id;
+
+    use super::*;
+
+    // One todo due on the last day there is
+    fn config() -> TodoConfig {
+        let mut config = TodoConfig::default();
+        let todo = &mut config.groups[0].todos[0];
+        todo.due = Some(Date::MAX.midnight().assume_offset(now().offset()));
+        todo.id = new_id();
+        config
+    }
+
+    #[test]
+    fn end_of_the_calendar() {
+        let mut config = config();
+        let id = config.groups[0].todos[0].id;
+        for week in [false, true] {
+            let mut calendar = CalendarView::new(Date::MAX, week, Some(id));
+            assert_eq!(calendar.days().last(), Some(&Date::MAX));
+
+            calendar.move_day(&config, 1);
+            calendar.move_page(&config, true);
+            assert_eq!(calendar.day, Date::MAX);
+
+            assert!(calendar.reschedule(&mut config, 1).is_err());
+            calendar.reschedule(&mut config, -1).unwrap();
+            assert_eq!(calendar.day, Date::MAX.previous_day().unwrap());
+            calendar.reschedule(&mut config, 1).unwrap();
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "P: Serialize", deserialize = "P: Deserialize<'de>"))] // C is never stored
 pub struct CommandGroup<P, C>
 where
     P: CommandPart<C>,
@@ -8,10 +9,23 @@ where
     pub commands: Vec<Command<P, C>>,
 }
 
+impl<P, C> Default for CommandGroup<P, C>
+where
+    P: CommandPart<C>,
+{
+    fn default() -> Self {
+        Self { commands: vec![] }
+    }
+}
+
 impl<P, C> CommandGroup<P, C>
 where
     P: CommandPart<C>,
 {
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
     pub fn to_strings(&self, context: &C) -> Vec<String> {
         let mut result = Vec::new();
         for command in &self.commands {
@@ -21,12 +35,14 @@ where
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "P: Serialize", deserialize = "P: Deserialize<'de>"))] // C is never stored
 pub struct Command<P, C>
 where
     P: CommandPart<C>,
 {
     pub parts: Vec<P>,
+    #[serde(skip)]
     _ctx_phantom: std::marker::PhantomData<C>,
 }
 
@@ -47,8 +63,13 @@ pub trait CommandPart<C> {
     fn to_string(&self, context: &C) -> String;
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CommandPartType {
     Direct(String),
     ConfigFile,
     ConfigFolder,
+    ItemId,
+    ItemName,
+    GroupPath,
+    DueDate,
 }
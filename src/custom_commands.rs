@@ -0,0 +1,361 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Stdout, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+use crossterm::{
+    cursor::MoveTo,
+    event::{read, Event},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{size, Clear, ClearType},
+};
+
+use crate::{
+    action::toggle_todo,
+    dates::{now, parse_date},
+    hooks::{config_folder, HookCommand, HookContext},
+    keybindings::Binding,
+    navigation::{Cursor, HierarchyItemEnum, HierarchyItemEnumMut, PositionHierarchy},
+    settings::Settings,
+    todo_config::{new_id, TodoConfig},
+};
+use time::OffsetDateTime;
+
+// A shell command bound to keys, run on the item under the cursor
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CustomCommand {
+    pub name: String, // Shown in the command palette and the output popup
+    #[serde(default)]
+    pub keys: Binding,
+    pub command: HookCommand,
+    #[serde(default)]
+    pub update_item: bool, // Read the last command's output as JSON changes to the item
+}
+
+// Fields a command can print to change the item, anything left out stays as it is.
+// {"name": "New name", "due": "tomorrow", "done": true}, a due of "" or "none" clears it.
+#[derive(serde::Deserialize)]
+struct ItemUpdate {
+    name: Option<String>,
+    due: Option<String>,
+    done: Option<bool>,
+}
+
+// A custom command running in the background, its commands one after another like `&&`.
+// Child processes like hooks, with what they print going to files so a chatty one can't fill a
+// pipe and stall.
+pub struct RunningCommand {
+    pub index: usize, // Into settings.custom_commands
+    item: u64,        // Id of the item it runs on, found again once it is done
+    update_item: bool,
+    folder: PathBuf,
+    commands: VecDeque<String>,               // Left to start
+    child: Option<(Child, PathBuf, PathBuf)>, // The one running now and its stdout and stderr files
+    output: String,
+    last_stdout: String,
+    failed: bool,
+}
+
+impl RunningCommand {
+    // Starts the command on the item under the cursor, other views run it on their selected todo
+    pub fn start(
+        index: usize,
+        command: &CustomCommand,
+        config: &TodoConfig,
+        cursor: &Cursor,
+        config_path: &Path,
+        settings: &Settings,
+    ) -> Result<Self> {
+        let h = cursor
+            .position(config, settings)
+            .ok_or_else(|| anyhow!("Nothing is selected to run {} on", command.name))?;
+        let context = context_for(config, &h, config_path)?;
+
+        let mut running = Self {
+            index,
+            item: h.find_item(config)?.item.id(),
+            update_item: command.update_item,
+            folder: config_folder(config_path),
+            commands: command.command.to_strings(&context).into(),
+            child: None,
+            output: String::new(),
+            last_stdout: String::new(),
+            failed: false,
+        };
+        running.poll()?;
+        Ok(running)
+    }
+
+    // Starts the next command once the last one is done, false when they all are
+    pub fn poll(&mut self) -> Result<bool> {
+        loop {
+            if let Some((child, out_path, err_path)) = &mut self.child {
+                let Some(status) = child.try_wait()? else {
+                    return Ok(true);
+                };
+                let stdout = std::fs::read_to_string(&*out_path).unwrap_or_default();
+                let stderr = std::fs::read_to_string(&*err_path).unwrap_or_default();
+                std::fs::remove_file(&*out_path).ok();
+                std::fs::remove_file(&*err_path).ok();
+                self.child = None;
+
+                self.output
+                    .push_str(&std::mem::replace(&mut self.last_stdout, stdout));
+                if status.success() {
+                    self.output.push_str(&stderr);
+                } else {
+                    self.output.push_str(&std::mem::take(&mut self.last_stdout));
+                    self.output.push_str(&stderr);
+                    self.output.push_str(&format!("\n{}", status));
+                    self.failed = true;
+                    self.commands.clear();
+                }
+            }
+
+            let Some(line) = self.commands.pop_front() else {
+                return Ok(false);
+            };
+            let out_path = output_path("out");
+            let err_path = output_path("err");
+            let child = Command::new("sh")
+                .arg("-c")
+                .arg(&line)
+                .current_dir(&self.folder)
+                .stdin(Stdio::null())
+                .stdout(File::create(&out_path)?)
+                .stderr(File::create(&err_path)?)
+                .spawn()?;
+            self.child = Some((child, out_path, err_path));
+        }
+    }
+
+    // Applies the update if it has one and returns what is left to show, and whether the data
+    // changed. The cursor stays on the item it was on.
+    pub fn finish(
+        mut self,
+        config: &mut TodoConfig,
+        cursor: &mut Cursor,
+        settings: &Settings,
+    ) -> (String, bool) {
+        let mut output = std::mem::take(&mut self.output);
+        if !self.update_item || self.failed {
+            output.push_str(&self.last_stdout);
+            return (output, false);
+        }
+
+        let on = cursor
+            .position(config, settings)
+            .and_then(|h| Some(h.find_item(config).ok()?.item.id()));
+        let result = PositionHierarchy::find_id(config, self.item)
+            .ok_or_else(|| anyhow!("The item it ran on is gone"))
+            .and_then(|mut h| apply_update(config, &mut h, &self.last_stdout));
+        if let (Cursor::Hierarchy(h), Some(id)) = (cursor, on) {
+            h.refind(config, id);
+        }
+
+        // The update itself isn't worth showing once it has been applied
+        match result {
+            Ok(()) => (output, true),
+            Err(e) => {
+                output.push_str(&self.last_stdout);
+                output.push_str(&format!("\nCouldn't update the item: {}", e));
+                (output, false)
+            }
+        }
+    }
+}
+
+// Quitting while it is still going stops it
+impl Drop for RunningCommand {
+    fn drop(&mut self) {
+        if let Some((mut child, out_path, err_path)) = self.child.take() {
+            child.kill().ok();
+            child.wait().ok();
+            std::fs::remove_file(out_path).ok();
+            std::fs::remove_file(err_path).ok();
+        }
+    }
+}
+
+fn output_path(stream: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("dmc-todo-{}.{}", new_id(), stream))
+}
+
+fn context_for(
+    config: &TodoConfig,
+    h: &PositionHierarchy,
+    config_path: &Path,
+) -> Result<HookContext> {
+    let (id, name, due) = match h.find_item(config)?.item {
+        HierarchyItemEnum::Todo(t) => (t.id, t.name.clone(), t.due),
+        HierarchyItemEnum::Group(g) => (g.id, g.name.clone(), None),
+    };
+
+    Ok(HookContext {
+        config_file: config_path.to_path_buf(),
+        item_id: Some(id),
+        item_name: name,
+        group_path: h.group_names(config).join(" > "),
+        due,
+    })
+}
+
+fn apply_update(config: &mut TodoConfig, h: &mut PositionHierarchy, json: &str) -> Result<()> {
+    let update: ItemUpdate = serde_json::from_str(json.trim())?;
+
+    let (id, done) = match h.find_item_mut(config)?.item {
+        HierarchyItemEnumMut::Todo(t) => {
+            if let Some(name) = update.name {
+                t.name = name;
+            }
+            if let Some(due) = update.due {
+                t.due = parse_due(&due)?;
+            }
+            (t.id, t.done_time.is_some())
+        }
+        HierarchyItemEnumMut::Group(g) => {
+            if update.done.is_some() {
+                return Err(anyhow!("Groups can't be done, only todos"));
+            }
+            if let Some(name) = update.name {
+                g.name = name;
+            }
+            if let Some(due) = update.due {
+                g.due = parse_due(&due)?;
+            }
+            return Ok(());
+        }
+    };
+
+    if update.done.is_some_and(|d| d != done) {
+        toggle_todo(config, h)?;
+        // The todo moved between todos and completed, keep the cursor on it
+        h.refind(config, id);
+    }
+
+    Ok(())
+}
+
+// "" or "none" for no due date
+fn parse_due(due: &str) -> Result<Option<OffsetDateTime>> {
+    if due.trim().is_empty() || due.trim() == "none" {
+        return Ok(None);
+    }
+    Ok(Some(parse_date(due, now()).ok_or_else(|| {
+        anyhow!("Can't read \"{}\" as a date", due)
+    })?))
+}
+
+pub fn show_output(stdout: &mut Stdout, title: &str, output: &str) -> Result<()> {
+    let (_, rows) = size()?;
+    let shown = rows.saturating_sub(3) as usize;
+
+    queue!(
+        stdout,
+        MoveTo(0, 0),
+        Clear(ClearType::All),
+        SetAttribute(Attribute::Reverse),
+        Print(format!(" {} ", title)),
+        SetAttribute(Attribute::Reset),
+        Print("\r\n")
+    )?;
+    for line in output.trim_end().lines().take(shown) {
+        queue!(stdout, Print(format!("{}\r\n", line)))?;
+    }
+    queue!(stdout, Print("\r\nPress any key to continue"))?;
+    stdout.flush()?;
+
+    while !(matches!(read()?, Event::Key(_))) {}
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(update_item: bool, line: &str) -> CustomCommand {
+        ron::from_str(&format!(
+            "(name: \"test\", update_item: {}, command: (commands: [(parts: [Direct({:?})])]))",
+            update_item, line
+        ))
+        .unwrap()
+    }
+
+    fn run(
+        config: &mut TodoConfig,
+        cursor: &mut Cursor,
+        command: &CustomCommand,
+    ) -> (String, bool) {
+        let settings = Settings::default();
+        let config_path = std::env::temp_dir().join("todo.ron");
+        let mut running =
+            RunningCommand::start(0, command, config, cursor, &config_path, &settings).unwrap();
+        while running.poll().unwrap() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        running.finish(config, cursor, &settings)
+    }
+
+    #[test]
+    fn updates() {
+        let mut config = TodoConfig::default();
+        let id = config.groups[0].todos[0].id;
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy::find_id(&config, id).unwrap());
+
+        let (output, changed) = run(
+            &mut config,
+            &mut cursor,
+            &command(true, r#"echo '{"name": "Renamed", "done": true}'"#),
+        );
+        assert_eq!((output.as_str(), changed), ("", true));
+        let h = PositionHierarchy::find_id(&config, id).unwrap();
+        match h.find_item(&config).unwrap().item {
+            HierarchyItemEnum::Todo(t) => assert!(t.name == "Renamed" && t.done_time.is_some()),
+            HierarchyItemEnum::Group(_) => panic!("not a todo"),
+        }
+        // The cursor followed it into completed
+        assert_eq!(
+            cursor
+                .position(&config, &Settings::default())
+                .unwrap()
+                .indexes,
+            h.indexes
+        );
+
+        let (output, changed) = run(
+            &mut config,
+            &mut cursor,
+            &command(false, "echo hi >&2; false"),
+        );
+        assert!(output.starts_with("hi\n") && !changed);
+    }
+
+    #[test]
+    fn groups_are_never_done() {
+        let mut config = TodoConfig::default();
+        let mut cursor = Cursor::Hierarchy(PositionHierarchy {
+            indexes: vec![0],
+            root: vec![],
+        });
+
+        let (output, changed) = run(
+            &mut config,
+            &mut cursor,
+            &command(true, r#"echo '{"done": true}'"#),
+        );
+        assert!(output.contains("Groups can't be done") && !changed);
+
+        run(
+            &mut config,
+            &mut cursor,
+            &command(true, r#"echo '{"due": "2023-03-01"}'"#),
+        );
+        assert!(config.groups[0].due.is_some());
+    }
+}
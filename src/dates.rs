@@ -0,0 +1,176 @@
+use time::{Date, Duration, OffsetDateTime, Time, Weekday};
+
+// The local time, or UTC when the offset can't be found out
+pub fn now() -> OffsetDateTime {
+    OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc())
+}
+
+// Reads the short date forms people type:
+// today, tomorrow, next week, monday..sunday, +3d / +2h / +1w (the + is optional),
+// 2023-03-01, 2023-03-01 14:30, 14:30.
+// Dates without a time are due at the end of that day.
+pub fn parse_date(input: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    parse_date_at(input, now, Time::from_hms(23, 59, 0).ok()?)
+}
+
+// The same forms, but dates without a time start at the beginning of that day
+pub fn parse_start(input: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    parse_date_at(input, now, Time::MIDNIGHT)
+}
+
+fn parse_date_at(input: &str, now: OffsetDateTime, time_of_day: Time) -> Option<OffsetDateTime> {
+    let input = input.trim().to_lowercase();
+    let on = |date: Date| now.replace_date(date).replace_time(time_of_day);
+
+    match input.as_str() {
+        "now" => return Some(now),
+        "today" => return Some(on(now.date())),
+        "tomorrow" => return Some(on(now.date().next_day()?)),
+        "next week" => return Some(on(now.date().checked_add(Duration::weeks(1))?)),
+        _ => {}
+    }
+
+    if let Some(weekday) = parse_weekday(&input) {
+        let mut date = now.date().next_day()?;
+        while date.weekday() != weekday {
+            date = date.next_day()?;
+        }
+        return Some(on(date));
+    }
+
+    let offset = input.strip_prefix('+').unwrap_or(&input);
+    if let Some(later) = parse_offset(offset, now) {
+        return Some(later);
+    }
+    if input.starts_with('+') {
+        return None;
+    }
+
+    let mut parts = input.split_whitespace();
+    let first = parts.next()?;
+    let second = parts.next();
+    if parts.next().is_some() {
+        return None;
+    }
+
+    match (parse_day(first), second) {
+        (Some(date), None) => Some(on(date)),
+        (Some(date), Some(time)) => Some(now.replace_date(date).replace_time(parse_time(time)?)),
+        (None, None) => Some(now.replace_time(parse_time(first)?)),
+        (None, Some(_)) => None,
+    }
+}
+
+// 3d, 2h, 1w, 30m
+fn parse_offset(input: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    let (amount, unit) = input.split_at(input.char_indices().last()?.0);
+    let amount: i64 = amount.trim().parse().ok()?;
+    let seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 604800,
+        _ => return None,
+    };
+    // Too far out to be a date is just not a date
+    now.checked_add(Duration::seconds(amount.checked_mul(seconds)?))
+}
+
+fn parse_weekday(input: &str) -> Option<Weekday> {
+    Some(match input {
+        "monday" | "mon" => Weekday::Monday,
+        "tuesday" | "tue" => Weekday::Tuesday,
+        "wednesday" | "wed" => Weekday::Wednesday,
+        "thursday" | "thu" => Weekday::Thursday,
+        "friday" | "fri" => Weekday::Friday,
+        "saturday" | "sat" => Weekday::Saturday,
+        "sunday" | "sun" => Weekday::Sunday,
+        _ => return None,
+    })
+}
+
+// YYYY-MM-DD
+fn parse_day(input: &str) -> Option<Date> {
+    let mut parts = input.split('-');
+    let year = parts.next()?.parse().ok()?;
+    let month: u8 = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Date::from_calendar_date(year, month.try_into().ok()?, day).ok()
+}
+
+// HH:MM
+fn parse_time(input: &str) -> Option<Time> {
+    let (hour, minute) = input.split_once(':')?;
+    Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Month;
+
+    use super::*;
+
+    fn at(month: Month, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2023, month, day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    fn noon() -> OffsetDateTime {
+        at(Month::March, 1, 12, 0)
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(
+            parse_offset("30m", noon()),
+            Some(at(Month::March, 1, 12, 30))
+        );
+        assert_eq!(parse_offset("2h", noon()), Some(at(Month::March, 1, 14, 0)));
+        assert_eq!(parse_offset("3d", noon()), Some(at(Month::March, 4, 12, 0)));
+        assert_eq!(parse_offset("1w", noon()), Some(at(Month::March, 8, 12, 0)));
+        assert_eq!(
+            parse_offset("-1d", noon()),
+            Some(at(Month::February, 28, 12, 0))
+        );
+        assert_eq!(parse_offset("3x", noon()), None);
+        assert_eq!(parse_offset("d", noon()), None);
+    }
+
+    #[test]
+    fn offsets_out_of_range() {
+        assert_eq!(parse_offset("99999999999999w", noon()), None);
+        assert_eq!(parse_offset("999999999d", noon()), None);
+        assert_eq!(parse_offset("-999999999d", noon()), None);
+        assert_eq!(parse_date("+99999999999999w", noon()), None);
+        assert_eq!(parse_start("999999999d", noon()), None);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(
+            parse_date("today", noon()),
+            Some(at(Month::March, 1, 23, 59))
+        );
+        assert_eq!(
+            parse_start("tomorrow", noon()),
+            Some(at(Month::March, 2, 0, 0))
+        );
+        assert_eq!(
+            parse_date("friday", noon()),
+            Some(at(Month::March, 3, 23, 59))
+        );
+        assert_eq!(
+            parse_date("2023-04-05 14:30", noon()),
+            Some(at(Month::April, 5, 14, 30))
+        );
+        assert_eq!(parse_date("9:15", noon()), Some(at(Month::March, 1, 9, 15)));
+        assert_eq!(parse_date("2023-02-30", noon()), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossterm::event::Event;
+
+use crate::{
+    display::truncate, frame::Frame, navigation::Cursor, theme::Theme, todo_config::TodoConfig,
+};
+
+// Internals shown in the top right corner, turned on with --debug or toggle_debug
+pub struct DebugInfo {
+    config_path: PathBuf,
+    pub last_event: Option<Event>,
+    pub render_time: Duration, // Of the frame before this one
+}
+
+impl DebugInfo {
+    pub fn new(config_path: &Path) -> Self {
+        Self {
+            config_path: config_path.to_path_buf(),
+            last_event: None,
+            render_time: Duration::ZERO,
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, config: &TodoConfig, cursor: &Cursor, theme: &Theme) {
+        let mut lines = vec![];
+
+        match cursor {
+            Cursor::Hierarchy(h) => {
+                lines.push(format!("indexes {:?}", h.indexes));
+                if !h.root.is_empty() {
+                    lines.push(format!("zoomed into {:?}", h.root));
+                }
+                lines.push(match h.vert_pos(config) {
+                    Ok(pos) => format!("vert_pos {}", pos),
+                    Err(e) => format!("vert_pos {}", e),
+                });
+            }
+            Cursor::List(list) => {
+                lines.push(format!("list {:?}", list.statuses));
+                lines.push(format!("selected {:?}", list.selected));
+            }
+            Cursor::Board(board) => {
+                lines.push(format!("board {} column {}", board.root, board.column));
+                lines.push(format!("selected {:?}", board.selected));
+            }
+            Cursor::Calendar(calendar) => {
+                lines.push(format!("calendar {} week {}", calendar.day, calendar.week));
+                lines.push(format!("selected {:?}", calendar.selected));
+            }
+            Cursor::Agenda(agenda) => lines.push(format!("agenda {:?}", agenda.selected)),
+        }
+
+        lines.push(match &self.last_event {
+            Some(event) => format!("event {:?}", event),
+            None => "event none".to_string(),
+        });
+        lines.push(format!("render {:.2?}", self.render_time));
+
+        let (groups, todos, done, archived) = config.groups.iter().fold((0, 0, 0, 0), |v, g| {
+            g.traverse(
+                v,
+                |g, _, (groups, todos, done, archived)| {
+                    (
+                        true,
+                        (groups + 1, todos, done, archived + g.todo_archive.len()),
+                    )
+                },
+                |t, _, (groups, todos, done, archived)| {
+                    if t.done_time.is_some() {
+                        (groups, todos, done + 1, archived)
+                    } else {
+                        (groups, todos + 1, done, archived)
+                    }
+                },
+                |_, _, v| v,
+                0,
+            )
+        });
+        lines.push(match std::fs::metadata(&self.config_path) {
+            Ok(meta) => format!("file {} bytes", meta.len()),
+            Err(e) => format!("file {}", e),
+        });
+        lines.push(format!(
+            "{} groups, {} todos, {} done, {} archived",
+            groups, todos, done, archived
+        ));
+
+        // A box against the right edge, under the status bar
+        let inner = frame.width().min(48).saturating_sub(1);
+        let x = frame.width().saturating_sub(inner + 1);
+        for (i, line) in lines.iter().enumerate() {
+            frame.move_to(x, i + 1);
+            frame.print(
+                format!(" {:<1$}", truncate(line, inner), inner),
+                theme.status_bar,
+            );
+        }
+    }
+}
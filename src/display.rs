@@ -0,0 +1,270 @@
+use time::{
+    format_description::{self, OwnedFormatItem},
+    Duration, OffsetDateTime,
+};
+use time_humanize::HumanTime;
+
+use crate::todo_config::Progress;
+
+// What the right-hand column of a group row shows, set in settings.ron.
+// "3/10  70% [#######---]  2 overdue"
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct GroupSummary {
+    pub counts: bool,  // Open out of total todos
+    pub percent: bool, // Percent done
+    pub bar: bool,
+    pub bar_width: usize,
+    pub overdue: bool, // Count of overdue todos, left out when there are none
+}
+
+impl Default for GroupSummary {
+    fn default() -> Self {
+        Self {
+            counts: true,
+            percent: false,
+            bar: true,
+            bar_width: 10,
+            overdue: true,
+        }
+    }
+}
+
+impl GroupSummary {
+    // The summary and the overdue part apart, so the overdue part can be coloured
+    pub fn render(&self, progress: Progress) -> (String, String) {
+        let total = progress.open + progress.done;
+        let mut parts = vec![];
+
+        if self.counts {
+            parts.push(format!("{}/{}", progress.open, total));
+        }
+        if self.percent {
+            let percent = (progress.done * 100).checked_div(total).unwrap_or(0);
+            parts.push(format!("{:>3}%", percent));
+        }
+        if self.bar && self.bar_width > 0 {
+            let filled = (progress.done * self.bar_width)
+                .checked_div(total)
+                .unwrap_or(0);
+            parts.push(format!(
+                "[{}{}]",
+                "#".repeat(filled),
+                "-".repeat(self.bar_width - filled)
+            ));
+        }
+
+        let overdue = if self.overdue && progress.overdue > 0 {
+            format!("  {} overdue", progress.overdue)
+        } else {
+            String::new()
+        };
+
+        (parts.join("  "), overdue)
+    }
+}
+
+// How close a due date has to be to fall in a tier. Tiers are checked in order and styled by
+// the theme's due_tiers in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DueWithin {
+    Today,
+    Tomorrow,
+    ThisWeek, // Up to the end of Sunday
+    Hours(i64),
+    Days(i64),
+}
+
+impl DueWithin {
+    pub fn contains(self, due: OffsetDateTime, now: OffsetDateTime) -> bool {
+        let day = due.to_offset(now.offset()).date();
+        // Past the end of the calendar everything is within
+        let until = |days: i64| {
+            now.date()
+                .checked_add(Duration::days(days))
+                .is_none_or(|last| day <= last)
+        };
+        // Huge numbers of hours or days in settings.ron are as good as forever
+        match self {
+            DueWithin::Today => day <= now.date(),
+            DueWithin::Tomorrow => until(1),
+            DueWithin::ThisWeek => until(6 - now.weekday().number_days_from_monday() as i64),
+            DueWithin::Hours(hours) => due - now < Duration::seconds(hours.saturating_mul(3_600)),
+            DueWithin::Days(days) => due - now < Duration::seconds(days.saturating_mul(86_400)),
+        }
+    }
+}
+
+pub fn default_due_tiers() -> Vec<DueWithin> {
+    vec![DueWithin::Hours(24)]
+}
+
+// Index of the first tier a not yet overdue date falls in
+pub fn due_tier(tiers: &[DueWithin], due: OffsetDateTime, now: OffsetDateTime) -> Option<usize> {
+    tiers.iter().position(|tier| tier.contains(due, now))
+}
+
+const DEFAULT_DATE_FORMAT: &str = "[year]-[month]-[day] [hour]:[minute]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DateDisplay {
+    Hidden,
+    Relative, // "in 2 days"
+    Absolute, // In `format`
+    Both,
+}
+
+// How due and completion times are written after a todo
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Dates {
+    pub due: DateDisplay,
+    pub done: DateDisplay,
+    pub format: DateFormat,
+}
+
+impl Default for Dates {
+    fn default() -> Self {
+        Self {
+            due: DateDisplay::Relative,
+            done: DateDisplay::Hidden,
+            format: DateFormat::default(),
+        }
+    }
+}
+
+// A time format description, "[year]-[month]-[day] [hour]:[minute]". Parsed once when settings
+// load so a broken one is reported there, not every time a date is drawn.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DateFormat {
+    text: String,
+    items: OwnedFormatItem,
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = time::error::InvalidFormatDescription;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let items = format_description::parse_owned::<1>(&text)?;
+        Ok(Self { text, items })
+    }
+}
+
+impl From<DateFormat> for String {
+    fn from(format: DateFormat) -> Self {
+        format.text
+    }
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        Self::try_from(DEFAULT_DATE_FORMAT.to_string()).expect("the default format parses")
+    }
+}
+
+impl PartialEq for DateFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for DateFormat {}
+
+impl std::fmt::Debug for DateFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+impl Dates {
+    // None when hidden
+    pub fn render(
+        &self,
+        display: DateDisplay,
+        time: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> Option<String> {
+        let relative = || HumanTime::from_seconds((time - now).whole_seconds()).to_string();
+        let absolute = || time.to_offset(now.offset()).format(&self.format.items).ok();
+
+        match display {
+            DateDisplay::Hidden => None,
+            DateDisplay::Relative => Some(relative()),
+            DateDisplay::Absolute => absolute(),
+            DateDisplay::Both => Some(match absolute() {
+                Some(absolute) => format!("{}, {}", absolute, relative()),
+                None => relative(),
+            }),
+        }
+    }
+}
+
+// Extra right-aligned columns on todo rows, set by `columns` in settings.ron.
+// With none, due and done times follow the name like before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Column {
+    Due,
+    Created,
+    Completed, // Time since it was done
+    Tags,      // #words, taken out of the name
+}
+
+impl Column {
+    pub fn width(self) -> usize {
+        match self {
+            Column::Due => 20,
+            Column::Created => 14,
+            Column::Completed => 14,
+            Column::Tags => 16,
+        }
+    }
+}
+
+// Cuts text to `width` characters, ending in an ellipsis when anything was cut
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut out: String = text.chars().take(width - 1).collect();
+    out.push('…');
+    out
+}
+
+// The name without its #tags, and the tags
+pub fn split_tags(name: &str) -> (String, Vec<&str>) {
+    let (tags, words): (Vec<&str>, Vec<&str>) = name
+        .split(' ')
+        .partition(|word| word.len() > 1 && word.starts_with('#'));
+    (words.join(" "), tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dates::now;
+
+    use super::*;
+
+    #[test]
+    fn huge_tiers() {
+        let now = now();
+        let due = now + Duration::days(400);
+        assert!(DueWithin::Hours(i64::MAX).contains(due, now));
+        assert!(DueWithin::Days(i64::MAX).contains(due, now));
+        assert!(!DueWithin::Days(3).contains(due, now));
+    }
+
+    #[test]
+    fn formats() {
+        let dates: Dates = ron::from_str(r#"(format: "[day]/[month]")"#).unwrap();
+        let time = OffsetDateTime::UNIX_EPOCH;
+        assert_eq!(
+            dates.render(DateDisplay::Absolute, time, time),
+            Some("01/01".to_string())
+        );
+        assert!(ron::from_str::<Dates>(r#"(format: "[nope]")"#).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::{fmt::Display, io::Stdout, io::Write};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::Print,
+    terminal::{Clear, ClearType},
+};
+
+use crate::theme::{reset, Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+const BLANK: Cell = Cell {
+    ch: ' ',
+    style: Style::PLAIN,
+};
+
+// One screen's worth of styled characters. Drawing goes here first and the renderer writes out
+// only what changed since the last frame. Anything past the edges is dropped.
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    x: usize,
+    y: usize,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![BLANK; width * height],
+            x: 0,
+            y: 0,
+        }
+    }
+
+    // The size of the terminal right now
+    pub fn for_terminal() -> Self {
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+        Self::new(width as usize, height as usize)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn move_to(&mut self, x: usize, y: usize) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn move_to_column(&mut self, x: usize) {
+        self.x = x;
+    }
+
+    pub fn newline(&mut self) {
+        self.x = 0;
+        self.y += 1;
+    }
+
+    pub fn print(&mut self, text: impl Display, style: Style) {
+        for ch in text.to_string().chars() {
+            if self.x < self.width && self.y < self.height {
+                self.cells[self.y * self.width + self.x] = Cell { ch, style };
+            }
+            self.x += 1;
+        }
+    }
+}
+
+// Keeps the last frame written so the next one only sends the cells that differ
+#[derive(Default)]
+pub struct Renderer {
+    last: Option<Frame>,
+}
+
+impl Renderer {
+    // For when something else drew over the screen, the next render repaints everything
+    pub fn invalidate(&mut self) {
+        self.last = None;
+    }
+
+    // For when something else drew on one row, like a prompt on the top line
+    pub fn invalidate_row(&mut self, y: usize) {
+        if let Some(last) = &mut self.last {
+            if y < last.height {
+                let start = y * last.width;
+                // Nothing drawn can match this, so every cell in the row gets written
+                last.cells[start..start + last.width].fill(Cell {
+                    ch: '\0',
+                    style: Style::PLAIN,
+                });
+            }
+        }
+    }
+
+    pub fn render(&mut self, out: &mut Stdout, frame: Frame) -> Result<()> {
+        // A new size moves everything, start over
+        let last = self
+            .last
+            .take()
+            .filter(|last| last.width == frame.width && last.height == frame.height);
+        if last.is_none() {
+            queue!(out, Clear(ClearType::All))?;
+        }
+
+        let mut style = Style::PLAIN;
+        for y in 0..frame.height {
+            let mut in_run = false; // Whether the terminal cursor is already where the next cell goes
+            for x in 0..frame.width {
+                let i = y * frame.width + x;
+                let cell = frame.cells[i];
+                let changed = match &last {
+                    Some(last) => last.cells[i] != cell,
+                    None => cell != BLANK, // Cleared already
+                };
+                if !changed {
+                    in_run = false;
+                    continue;
+                }
+
+                if !in_run {
+                    queue!(out, MoveTo(x as u16, y as u16))?;
+                    in_run = true;
+                }
+                if cell.style != style {
+                    reset(out);
+                    cell.style.apply(out);
+                    style = cell.style;
+                }
+                queue!(out, Print(cell.ch))?;
+            }
+        }
+        reset(out);
+        out.flush()?;
+
+        self.last = Some(frame);
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use time::{format_description, Duration, OffsetDateTime};
+
+use crate::{
+    command_manager::{CommandGroup, CommandPart, CommandPartType},
+    dates::now,
+    todo_config::TodoConfig,
+};
+
+pub type HookCommand = CommandGroup<CommandPartType, HookContext>;
+
+// Commands to run when things happen, set in settings.ron
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HookCommands {
+    pub on_save: HookCommand,
+    pub on_todo_completed: HookCommand,
+    pub on_todo_added: HookCommand,
+    pub on_due_soon: HookCommand,
+    pub due_soon_within: Duration, // How long before the due date on_due_soon runs
+}
+
+impl Default for HookCommands {
+    fn default() -> Self {
+        Self {
+            on_save: Default::default(),
+            on_todo_completed: Default::default(),
+            on_todo_added: Default::default(),
+            on_due_soon: Default::default(),
+            due_soon_within: Duration::hours(1),
+        }
+    }
+}
+
+// What a hook's placeholders are filled in with. The item fields are empty for on_save.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookContext {
+    pub config_file: PathBuf,
+    pub item_id: Option<u64>,
+    pub item_name: String,
+    pub group_path: String, // "School > AP CSP"
+    pub due: Option<OffsetDateTime>,
+}
+
+impl CommandPart<HookContext> for CommandPartType {
+    // Everything but Direct is quoted for sh so names with spaces or quotes stay one argument
+    fn to_string(&self, context: &HookContext) -> String {
+        match self {
+            CommandPartType::Direct(text) => text.clone(),
+            CommandPartType::ConfigFile => quote(&context.config_file.to_string_lossy()),
+            CommandPartType::ConfigFolder => {
+                quote(&config_folder(&context.config_file).to_string_lossy())
+            }
+            CommandPartType::ItemId => context.item_id.map(|id| id.to_string()).unwrap_or_default(),
+            CommandPartType::ItemName => quote(&context.item_name),
+            CommandPartType::GroupPath => quote(&context.group_path),
+            CommandPartType::DueDate => quote(
+                &context
+                    .due
+                    .and_then(|due| {
+                        let format =
+                            format_description::parse("[year]-[month]-[day] [hour]:[minute]")
+                                .ok()?;
+                        due.format(&format).ok()
+                    })
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+pub fn config_folder(config_file: &Path) -> PathBuf {
+    config_file
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+struct TodoState {
+    done: bool,
+    name: String,
+    group_path: String,
+    due: Option<OffsetDateTime>,
+}
+
+// Groups with a due date by id, done once nothing in them is left to do
+fn due_groups(config: &TodoConfig) -> BTreeMap<u64, TodoState> {
+    let mut state = (Vec::new(), BTreeMap::new());
+    for group in config.groups.iter() {
+        state = group.traverse(
+            state,
+            |g, _d, (mut path, mut groups): (Vec<String>, BTreeMap<u64, TodoState>)| {
+                if g.due.is_some() {
+                    groups.insert(
+                        g.id,
+                        TodoState {
+                            done: g.todo_count() == 0,
+                            name: g.name.clone(),
+                            group_path: path.join(" > "),
+                            due: g.due,
+                        },
+                    );
+                }
+                path.push(g.name.clone());
+                (true, (path, groups))
+            },
+            |_t, _d, v| v,
+            |_g, _d, (mut path, groups)| {
+                path.pop();
+                (path, groups)
+            },
+            0,
+        );
+    }
+    state.1
+}
+
+// Every todo by id, taken before an action to see what it added or completed
+pub struct TodoSnapshot(BTreeMap<u64, TodoState>);
+
+impl TodoSnapshot {
+    pub fn take(config: &TodoConfig) -> Self {
+        let mut state = (Vec::new(), BTreeMap::new());
+        for group in config.groups.iter() {
+            state = group.traverse(
+                state,
+                |g, _d, (mut path, todos)| {
+                    path.push(g.name.clone());
+                    (true, (path, todos))
+                },
+                |t, _d, (path, mut todos): (Vec<String>, BTreeMap<u64, TodoState>)| {
+                    todos.insert(
+                        t.id,
+                        TodoState {
+                            done: t.done_time.is_some(),
+                            name: t.name.clone(),
+                            group_path: path.join(" > "),
+                            due: t.due,
+                        },
+                    );
+                    (path, todos)
+                },
+                |_g, _d, (mut path, todos)| {
+                    path.pop();
+                    (path, todos)
+                },
+                0,
+            );
+        }
+        Self(state.1)
+    }
+}
+
+// Runs hook commands in the background, each group's commands one after another in order, so a
+// slow `git commit` doesn't hold up the UI. Child processes rather than threads, the local time
+// can't be found out while the process has more than one thread.
+pub struct HookRunner {
+    hooks: HookCommands,
+    config_file: PathBuf,
+    running: Vec<RunningHook>,
+    due_soon_sent: BTreeSet<u64>, // Todos and groups on_due_soon already ran for this session
+}
+
+impl HookRunner {
+    pub fn new(hooks: HookCommands, config_file: &Path) -> Self {
+        Self {
+            hooks,
+            config_file: config_file.to_path_buf(),
+            running: vec![],
+            due_soon_sent: BTreeSet::new(),
+        }
+    }
+
+    pub fn saved(&mut self) {
+        let context = HookContext {
+            config_file: self.config_file.clone(),
+            ..Default::default()
+        };
+        self.run(self.hooks.on_save.to_strings(&context));
+    }
+
+    // Compares against the todos from before an action
+    pub fn after_action(&mut self, before: &TodoSnapshot, config: &TodoConfig) {
+        if self.hooks.on_todo_added.is_empty() && self.hooks.on_todo_completed.is_empty() {
+            return;
+        }
+
+        let after = TodoSnapshot::take(config);
+        for (id, todo) in after.0.iter() {
+            let hook = match before.0.get(id) {
+                None => &self.hooks.on_todo_added,
+                Some(old) if todo.done && !old.done => &self.hooks.on_todo_completed,
+                Some(_) => continue,
+            };
+            let commands = hook.to_strings(&self.context_for(*id, todo));
+            self.run(commands);
+        }
+    }
+
+    // Called every loop, runs on_due_soon once for each todo or group that comes within
+    // due_soon_within
+    pub fn tick(&mut self, config: &TodoConfig) {
+        self.running.retain_mut(RunningHook::poll);
+        if self.hooks.on_due_soon.is_empty() {
+            return;
+        }
+
+        let now = now();
+        let snapshot = TodoSnapshot::take(config);
+        let groups = due_groups(config);
+        for (id, todo) in snapshot.0.iter().chain(groups.iter()) {
+            let due_soon = match todo.due {
+                Some(due) => !todo.done && due >= now && due - now <= self.hooks.due_soon_within,
+                None => false,
+            };
+            if due_soon && self.due_soon_sent.insert(*id) {
+                let commands = self
+                    .hooks
+                    .on_due_soon
+                    .to_strings(&self.context_for(*id, todo));
+                self.run(commands);
+            }
+        }
+    }
+
+    // Waits for anything still running, so quitting doesn't cut off the last on_save
+    pub fn finish(self) {
+        for mut hook in self.running {
+            while hook.poll() {
+                if let Some(child) = &mut hook.child {
+                    child.wait().ok();
+                }
+            }
+        }
+    }
+
+    fn context_for(&self, id: u64, todo: &TodoState) -> HookContext {
+        HookContext {
+            config_file: self.config_file.clone(),
+            item_id: Some(id),
+            item_name: todo.name.clone(),
+            group_path: todo.group_path.clone(),
+            due: todo.due,
+        }
+    }
+
+    fn run(&mut self, commands: Vec<String>) {
+        if commands.is_empty() {
+            return;
+        }
+
+        let mut hook = RunningHook {
+            folder: config_folder(&self.config_file),
+            commands: commands.into(),
+            child: None,
+        };
+        if hook.poll() {
+            self.running.push(hook);
+        }
+    }
+}
+
+// A group of hook commands part of the way through
+struct RunningHook {
+    folder: PathBuf,
+    commands: VecDeque<String>, // Left to start
+    child: Option<Child>,       // The one running now
+}
+
+impl RunningHook {
+    // Starts the next command once the last one is done, false when they all are
+    fn poll(&mut self) -> bool {
+        loop {
+            if let Some(child) = &mut self.child {
+                if let Ok(None) = child.try_wait() {
+                    return true;
+                }
+            }
+            let Some(command) = self.commands.pop_front() else {
+                self.child = None;
+                return false;
+            };
+            // One that fails to start is skipped like one that fails
+            self.child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(&self.folder)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok();
+        }
+    }
+}
@@ -0,0 +1,868 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{action::Action, custom_commands::CustomCommand};
+
+// One key with the modifiers held for it. Written as "C-s", "A-q", "S-Tab", "g", "PageUp".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+// Keys pressed one after another. Written space separated, "g g".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<KeyPress>);
+
+// Every sequence that triggers an action. Written as one string or a list of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Binding(pub Vec<KeySequence>);
+
+// Where the cursor has to be for an action to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingContext {
+    Global,
+    Group, // On a group
+    Todo,  // On a todo
+}
+
+// Two actions that can't both be reached in some context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub context: BindingContext,
+    pub shadowed: String, // Action names
+    pub by: String,
+    pub sequence: KeySequence, // The shadowed action's sequence
+    pub blocker: KeySequence,  // The other action's sequence, the same or a prefix
+}
+
+pub enum ChordState {
+    Complete, // The keys are a whole binding
+    Pending,  // The keys start at least one binding
+    NoMatch,
+}
+
+impl BindingContext {
+    // The context both apply in, None if they never apply at the same time
+    fn overlap(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Global, c) | (c, Self::Global) => Some(c),
+            (a, b) if a == b => Some(a),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BindingContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "everywhere"),
+            Self::Group => write!(f, "on groups"),
+            Self::Todo => write!(f, "on todos"),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sequence == self.blocker {
+            write!(
+                f,
+                "{} shadows {}, both are bound to \"{}\" {}",
+                self.by, self.shadowed, self.sequence, self.context
+            )
+        } else {
+            write!(
+                f,
+                "{} \"{}\" can't be reached {}, \"{}\" runs {} first",
+                self.shadowed, self.sequence, self.context, self.blocker, self.by
+            )
+        }
+    }
+}
+
+impl KeyPress {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shifted characters already arrive as the shifted char
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                return Self {
+                    code: KeyCode::Char(c.to_ascii_uppercase()),
+                    modifiers: modifiers - KeyModifiers::SHIFT,
+                };
+            }
+        }
+        if code == KeyCode::BackTab {
+            return Self {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            };
+        }
+
+        Self { code, modifiers }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut rest = input;
+        let mut modifiers = KeyModifiers::NONE;
+
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            modifiers |= match rest.as_bytes()[0] {
+                b'C' => KeyModifiers::CONTROL,
+                b'A' | b'M' => KeyModifiers::ALT,
+                b'S' => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            rest = &rest[2..];
+        }
+
+        let code = match rest {
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Tab" => KeyCode::Tab,
+            "BackTab" => KeyCode::BackTab,
+            "Backspace" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Insert" => KeyCode::Insert,
+            "Space" => KeyCode::Char(' '),
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some('F'), Some(_)) => KeyCode::F(
+                        rest[1..]
+                            .parse()
+                            .map_err(|_| format!("Unknown key \"{}\"", input))?,
+                    ),
+                    _ => return Err(format!("Unknown key \"{}\"", input)),
+                }
+            }
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Up => write!(f, "Up"),
+            KeyCode::Down => write!(f, "Down"),
+            KeyCode::Left => write!(f, "Left"),
+            KeyCode::Right => write!(f, "Right"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "BackTab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Delete"),
+            KeyCode::Insert => write!(f, "Insert"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl KeySequence {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let keys = input
+            .split_whitespace()
+            .map(KeyPress::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if keys.is_empty() {
+            return Err("Empty key sequence".to_string());
+        }
+
+        Ok(Self(keys))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        Ok(())
+    }
+}
+
+impl Binding {
+    // Panics on a bad sequence, only meant for the built-in defaults
+    fn from_str(input: &str) -> Self {
+        Self::from_strs(&[input])
+    }
+
+    fn from_strs(inputs: &[&str]) -> Self {
+        Self(
+            inputs
+                .iter()
+                .map(|input| KeySequence::parse(input).expect("Default binding to parse."))
+                .collect(),
+        )
+    }
+
+    pub fn matches(&self, keys: &[KeyPress]) -> bool {
+        self.0.iter().any(|s| s.0 == keys)
+    }
+
+    pub fn starts_with(&self, keys: &[KeyPress]) -> bool {
+        self.0
+            .iter()
+            .any(|s| s.0.len() > keys.len() && s.0.starts_with(keys))
+    }
+}
+
+impl From<KeyPress> for Binding {
+    fn from(key: KeyPress) -> Self {
+        Self(vec![KeySequence(vec![key])])
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, sequence) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", sequence)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Binding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let [sequence] = self.0.as_slice() {
+            serializer.serialize_str(&sequence.to_string())
+        } else {
+            serializer.collect_seq(self.0.iter().map(|s| s.to_string()))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Binding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BindingVisitor;
+
+        impl<'de> de::Visitor<'de> for BindingVisitor {
+            type Value = Binding;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a key sequence like \"C-s\" or a list of them")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Binding, E> {
+                Ok(Binding(vec![KeySequence::parse(v).map_err(E::custom)?]))
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Binding, A::Error> {
+                let mut sequences = vec![];
+                while let Some(s) = seq.next_element::<String>()? {
+                    sequences.push(KeySequence::parse(&s).map_err(de::Error::custom)?);
+                }
+                Ok(Binding(sequences))
+            }
+        }
+
+        deserializer.deserialize_any(BindingVisitor)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Keybindings {
+    #[serde(default = "default_add_todo")]
+    pub add_todo: Binding,
+    #[serde(default = "default_add_group")]
+    pub add_group: Binding,
+    #[serde(default = "default_add_top_group")]
+    pub add_top_group: Binding,
+    #[serde(default = "default_toggle_group")]
+    pub toggle_group: Binding,
+    #[serde(default = "default_toggle_todo")]
+    pub toggle_todo: Binding,
+    #[serde(default = "default_archive_todo")]
+    pub archive_todo: Binding,
+    #[serde(default = "default_hide_group")]
+    pub hide_group: Binding,
+    #[serde(default = "default_edit_todo")]
+    pub edit_todo: Binding,
+    #[serde(default = "default_edit_group")]
+    pub edit_group: Binding,
+    #[serde(default = "default_move_todo_up")]
+    pub move_todo_up: Binding,
+    #[serde(default = "default_move_todo_down")]
+    pub move_todo_down: Binding,
+    #[serde(default = "default_move_group_up")]
+    pub move_group_up: Binding,
+    #[serde(default = "default_move_group_down")]
+    pub move_group_down: Binding,
+    #[serde(default = "default_cursor_up")]
+    pub cursor_up: Binding,
+    #[serde(default = "default_cursor_down")]
+    pub cursor_down: Binding,
+    #[serde(default = "default_group_up")]
+    pub group_up: Binding,
+    #[serde(default = "default_group_down")]
+    pub group_down: Binding,
+    #[serde(default = "default_hierarchy_up")]
+    pub hierarchy_up: Binding,
+    #[serde(default = "default_hierarchy_down")]
+    pub hierarchy_down: Binding,
+    #[serde(default = "default_quit")]
+    pub quit: Binding,
+    #[serde(default = "default_quit_without_saving")]
+    pub quit_without_saving: Binding,
+    #[serde(default = "default_save")]
+    pub save: Binding,
+    #[serde(default = "default_clean")]
+    pub clean: Binding,
+    #[serde(default = "default_help")]
+    pub help: Binding,
+    #[serde(default = "default_command_palette")]
+    pub command_palette: Binding,
+    #[serde(default)]
+    pub move_to: Binding, // Unbound by default, mostly used from the palette
+    #[serde(default)]
+    pub set_due: Binding,
+    #[serde(default = "default_snooze")]
+    pub snooze: Binding,
+    #[serde(default = "default_toggle_debug")]
+    pub toggle_debug: Binding,
+    #[serde(default = "default_cycle_status")]
+    pub cycle_status: Binding,
+    #[serde(default)]
+    pub set_status: Binding,
+    #[serde(default = "default_filter_status")]
+    pub filter_status: Binding,
+    #[serde(default = "default_tree_view")]
+    pub tree_view: Binding,
+    #[serde(default = "default_board_view")]
+    pub board_view: Binding,
+    #[serde(default = "default_cursor_left")]
+    pub cursor_left: Binding,
+    #[serde(default = "default_cursor_right")]
+    pub cursor_right: Binding,
+    #[serde(default = "default_move_left")]
+    pub move_left: Binding,
+    #[serde(default = "default_move_right")]
+    pub move_right: Binding,
+    #[serde(default = "default_calendar_view")]
+    pub calendar_view: Binding,
+    #[serde(default = "default_agenda_view")]
+    pub agenda_view: Binding,
+    #[serde(default = "default_set_priority")]
+    pub set_priority: Binding,
+    #[serde(default = "default_zoom_in")]
+    pub zoom_in: Binding,
+    #[serde(default = "default_zoom_out")]
+    pub zoom_out: Binding,
+}
+
+fn default_add_todo() -> Binding {
+    Binding::from_str("a")
+}
+fn default_add_group() -> Binding {
+    Binding::from_str("g")
+}
+fn default_add_top_group() -> Binding {
+    Binding::from_str("n")
+}
+fn default_toggle_group() -> Binding {
+    Binding::from_str("Space")
+}
+fn default_toggle_todo() -> Binding {
+    Binding::from_str("Space")
+}
+fn default_archive_todo() -> Binding {
+    Binding::from_str("d")
+}
+fn default_hide_group() -> Binding {
+    Binding::from_str("x")
+}
+fn default_edit_todo() -> Binding {
+    Binding::from_str("e")
+}
+fn default_edit_group() -> Binding {
+    Binding::from_str("e")
+}
+fn default_move_todo_up() -> Binding {
+    Binding::from_strs(&["i", "S-Up"])
+}
+fn default_move_todo_down() -> Binding {
+    Binding::from_strs(&["k", "S-Down"])
+}
+fn default_move_group_up() -> Binding {
+    Binding::from_strs(&["i", "S-Up"])
+}
+fn default_move_group_down() -> Binding {
+    Binding::from_strs(&["k", "S-Down"])
+}
+fn default_cursor_up() -> Binding {
+    Binding::from_str("Up")
+}
+fn default_cursor_down() -> Binding {
+    Binding::from_str("Down")
+}
+fn default_group_up() -> Binding {
+    Binding::from_str("PageUp")
+}
+fn default_group_down() -> Binding {
+    Binding::from_str("PageDown")
+}
+fn default_hierarchy_up() -> Binding {
+    Binding::from_str("[")
+}
+fn default_hierarchy_down() -> Binding {
+    Binding::from_str("]")
+}
+fn default_quit() -> Binding {
+    Binding::from_str("q")
+}
+fn default_quit_without_saving() -> Binding {
+    Binding::from_str("A-q")
+}
+fn default_save() -> Binding {
+    Binding::from_str("s")
+}
+fn default_clean() -> Binding {
+    Binding::from_str("A-o")
+}
+fn default_help() -> Binding {
+    Binding::from_str("h")
+}
+fn default_command_palette() -> Binding {
+    Binding::from_str(":")
+}
+fn default_snooze() -> Binding {
+    Binding::from_str("z")
+}
+fn default_toggle_debug() -> Binding {
+    Binding::from_str("F12")
+}
+fn default_cycle_status() -> Binding {
+    Binding::from_str("c")
+}
+fn default_filter_status() -> Binding {
+    Binding::from_str("f")
+}
+fn default_tree_view() -> Binding {
+    Binding::from_str("t")
+}
+fn default_board_view() -> Binding {
+    Binding::from_str("b")
+}
+fn default_calendar_view() -> Binding {
+    Binding::from_str("v")
+}
+fn default_agenda_view() -> Binding {
+    Binding::from_str("u")
+}
+fn default_set_priority() -> Binding {
+    Binding::from_str("p")
+}
+fn default_zoom_in() -> Binding {
+    Binding::from_str(">")
+}
+fn default_zoom_out() -> Binding {
+    Binding::from_str("<")
+}
+fn default_cursor_left() -> Binding {
+    Binding::from_str("Left")
+}
+fn default_cursor_right() -> Binding {
+    Binding::from_str("Right")
+}
+fn default_move_left() -> Binding {
+    Binding::from_strs(&["j", "S-Left"])
+}
+fn default_move_right() -> Binding {
+    Binding::from_strs(&["l", "S-Right"])
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            add_todo: default_add_todo(),
+            add_group: default_add_group(),
+            add_top_group: default_add_top_group(),
+            toggle_group: default_toggle_group(),
+            toggle_todo: default_toggle_todo(),
+            archive_todo: default_archive_todo(),
+            hide_group: default_hide_group(),
+            edit_todo: default_edit_todo(),
+            edit_group: default_edit_group(),
+            move_todo_up: default_move_todo_up(),
+            move_todo_down: default_move_todo_down(),
+            move_group_up: default_move_group_up(),
+            move_group_down: default_move_group_down(),
+            cursor_up: default_cursor_up(),
+            cursor_down: default_cursor_down(),
+            group_up: default_group_up(),
+            group_down: default_group_down(),
+            hierarchy_up: default_hierarchy_up(),
+            hierarchy_down: default_hierarchy_down(),
+            quit: default_quit(),
+            quit_without_saving: default_quit_without_saving(),
+            save: default_save(),
+            clean: default_clean(),
+            help: default_help(),
+            command_palette: default_command_palette(),
+            move_to: Binding::default(),
+            set_due: Binding::default(),
+            snooze: default_snooze(),
+            toggle_debug: default_toggle_debug(),
+            cycle_status: default_cycle_status(),
+            set_status: Binding::default(),
+            filter_status: default_filter_status(),
+            tree_view: default_tree_view(),
+            board_view: default_board_view(),
+            cursor_left: default_cursor_left(),
+            cursor_right: default_cursor_right(),
+            move_left: default_move_left(),
+            move_right: default_move_right(),
+            calendar_view: default_calendar_view(),
+            agenda_view: default_agenda_view(),
+            set_priority: default_set_priority(),
+            zoom_in: default_zoom_in(),
+            zoom_out: default_zoom_out(),
+        }
+    }
+}
+
+impl Keybindings {
+    // Every bound action, in the order they are listed in settings.ron.
+    // When a key fits more than one action the first one here runs.
+    pub fn entries(&self) -> Vec<(Action, &Binding)> {
+        vec![
+            (Action::AddTodo, &self.add_todo),
+            (Action::AddGroup, &self.add_group),
+            (Action::AddTopGroup, &self.add_top_group),
+            (Action::ToggleGroup, &self.toggle_group),
+            (Action::ToggleTodo, &self.toggle_todo),
+            (Action::ArchiveTodo, &self.archive_todo),
+            (Action::HideGroup, &self.hide_group),
+            (Action::EditTodo, &self.edit_todo),
+            (Action::EditGroup, &self.edit_group),
+            (Action::MoveTodoUp, &self.move_todo_up),
+            (Action::MoveTodoDown, &self.move_todo_down),
+            (Action::MoveGroupUp, &self.move_group_up),
+            (Action::MoveGroupDown, &self.move_group_down),
+            (Action::CursorUp, &self.cursor_up),
+            (Action::CursorDown, &self.cursor_down),
+            (Action::GroupUp, &self.group_up),
+            (Action::GroupDown, &self.group_down),
+            (Action::HierarchyUp, &self.hierarchy_up),
+            (Action::HierarchyDown, &self.hierarchy_down),
+            (Action::Quit, &self.quit),
+            (Action::QuitWithoutSaving, &self.quit_without_saving),
+            (Action::Save, &self.save),
+            (Action::Clean, &self.clean),
+            (Action::Help, &self.help),
+            (Action::CommandPalette, &self.command_palette),
+            (Action::MoveTo, &self.move_to),
+            (Action::SetDue, &self.set_due),
+            (Action::Snooze, &self.snooze),
+            (Action::ToggleDebug, &self.toggle_debug),
+            (Action::CycleStatus, &self.cycle_status),
+            (Action::SetStatus, &self.set_status),
+            (Action::FilterStatus, &self.filter_status),
+            (Action::TreeView, &self.tree_view),
+            (Action::BoardView, &self.board_view),
+            (Action::CursorLeft, &self.cursor_left),
+            (Action::CursorRight, &self.cursor_right),
+            (Action::MoveLeft, &self.move_left),
+            (Action::MoveRight, &self.move_right),
+            (Action::CalendarView, &self.calendar_view),
+            (Action::AgendaView, &self.agenda_view),
+            (Action::SetPriority, &self.set_priority),
+            (Action::ZoomIn, &self.zoom_in),
+            (Action::ZoomOut, &self.zoom_out),
+        ]
+    }
+}
+
+// Everything a key can run, the built-in actions then custom commands
+pub struct Bindings<'a>(pub Vec<(Action, &'a str, &'a Binding)>);
+
+impl<'a> Bindings<'a> {
+    pub fn new(keybindings: &'a Keybindings, custom_commands: &'a [CustomCommand]) -> Self {
+        let mut entries: Vec<(Action, &str, &Binding)> = keybindings
+            .entries()
+            .into_iter()
+            .map(|(action, binding)| (action, action.name(), binding))
+            .collect();
+        for (i, command) in custom_commands.iter().enumerate() {
+            entries.push((Action::Custom(i), &command.name, &command.keys));
+        }
+        Self(entries)
+    }
+
+    // Pairs of bindings that stop each other from running where both apply
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+
+        for (i, (action, name, binding)) in self.0.iter().enumerate() {
+            for (j, (other, other_name, other_binding)) in self.0.iter().enumerate() {
+                let Some(shared) = action.context().overlap(other.context()) else {
+                    continue;
+                };
+                if i == j {
+                    continue;
+                }
+
+                for sequence in binding.0.iter() {
+                    for blocker in other_binding.0.iter() {
+                        let same = sequence == blocker;
+                        let prefix = blocker.0.len() < sequence.0.len()
+                            && sequence.0.starts_with(&blocker.0);
+
+                        // Report identical sequences once per pair
+                        if prefix || (same && i > j) {
+                            conflicts.push(Conflict {
+                                context: shared,
+                                shadowed: name.to_string(),
+                                by: other_name.to_string(),
+                                sequence: sequence.clone(),
+                                blocker: blocker.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    // The first action bound to exactly these keys that applies where the cursor is
+    pub fn resolve(&self, keys: &[KeyPress], on: BindingContext) -> Option<Action> {
+        self.active(on)
+            .find(|(_, _, binding)| binding.matches(keys))
+            .map(|(action, _, _)| *action)
+    }
+
+    // Adds a key to the ones pressed so far. A key that doesn't go on from them starts over as
+    // it may begin a binding of its own, and keys that can't become a binding are dropped. Only
+    // the bindings that apply where the cursor is count, so a chord isn't cut short by a
+    // binding for somewhere else.
+    pub fn press(
+        &self,
+        pending: &mut Vec<KeyPress>,
+        key: KeyPress,
+        on: BindingContext,
+    ) -> ChordState {
+        pending.push(key);
+        let mut state = self.chord_state(pending, on);
+        if matches!(state, ChordState::NoMatch) && pending.len() > 1 {
+            *pending = vec![key];
+            state = self.chord_state(pending, on);
+        }
+        if matches!(state, ChordState::NoMatch) {
+            pending.clear();
+        }
+        state
+    }
+
+    pub fn chord_state(&self, keys: &[KeyPress], on: BindingContext) -> ChordState {
+        if self.active(on).any(|(_, _, b)| b.matches(keys)) {
+            ChordState::Complete
+        } else if self.active(on).any(|(_, _, b)| b.starts_with(keys)) {
+            ChordState::Pending
+        } else {
+            ChordState::NoMatch
+        }
+    }
+
+    // The bindings that apply where the cursor is
+    fn active(&self, on: BindingContext) -> impl Iterator<Item = &(Action, &'a str, &'a Binding)> {
+        self.0.iter().filter(move |(action, _, _)| {
+            action.context() == BindingContext::Global || action.context() == on
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyPress {
+        KeyPress::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(KeyPress::parse("g"), Ok(key('g')));
+        assert_eq!(
+            KeyPress::parse("C-s"),
+            Ok(KeyPress::new(KeyCode::Char('s'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            KeyPress::parse("M-C-Left"),
+            Ok(KeyPress::new(
+                KeyCode::Left,
+                KeyModifiers::ALT | KeyModifiers::CONTROL
+            ))
+        );
+        assert_eq!(KeyPress::parse("Space"), Ok(key(' ')));
+        assert_eq!(
+            KeyPress::parse("F12"),
+            Ok(KeyPress::new(KeyCode::F(12), KeyModifiers::NONE))
+        );
+        // A lone dash is a key, not a modifier
+        assert_eq!(KeyPress::parse("-"), Ok(key('-')));
+        assert!(KeyPress::parse("Fx").is_err());
+        assert!(KeyPress::parse("Nope").is_err());
+    }
+
+    #[test]
+    fn shift_makes_capitals() {
+        assert_eq!(KeyPress::parse("S-a"), Ok(key('A')));
+        assert_eq!(
+            KeyPress::new(KeyCode::Char('a'), KeyModifiers::SHIFT),
+            key('A')
+        );
+        assert_eq!(
+            KeyPress::parse("S-Up"),
+            Ok(KeyPress::new(KeyCode::Up, KeyModifiers::SHIFT))
+        );
+    }
+
+    #[test]
+    fn sequences_round_trip() {
+        for input in ["g h", "C-x C-s", "A-q", "S-Tab", "Space", "F1 PageDown"] {
+            assert_eq!(KeySequence::parse(input).unwrap().to_string(), input);
+        }
+        assert!(KeySequence::parse("  ").is_err());
+        assert!(KeySequence::parse("g Nope").is_err());
+    }
+
+    #[test]
+    fn bindings_from_settings() {
+        let keybindings: Keybindings =
+            ron::from_str(r#"(save: ["s", "C-s"], help: "g h")"#).unwrap();
+        assert_eq!(keybindings.save.to_string(), "s, C-s");
+        assert!(keybindings.help.matches(&[key('g'), key('h')]));
+        assert!(keybindings.help.starts_with(&[key('g')]));
+        assert!(!keybindings.help.starts_with(&[key('g'), key('h')]));
+        // Left out ones keep their defaults
+        assert_eq!(keybindings.quit, Keybindings::default().quit);
+        assert!(ron::from_str::<Keybindings>(r#"(save: "C-")"#).is_err());
+    }
+
+    #[test]
+    fn chords() {
+        let keybindings: Keybindings =
+            ron::from_str(r#"(archive_todo: "d d", help: "g h", save: "s")"#).unwrap();
+        let bindings = Bindings::new(&keybindings, &[]);
+        let mut pending = vec![];
+
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Todo),
+            ChordState::Pending
+        ));
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Todo),
+            ChordState::Complete
+        ));
+        assert_eq!(
+            bindings.resolve(&pending, BindingContext::Todo),
+            Some(Action::ArchiveTodo)
+        );
+
+        // A key that breaks a chord can start the next one
+        pending.clear();
+        bindings.press(&mut pending, key('g'), BindingContext::Todo);
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Todo),
+            ChordState::Pending
+        ));
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Todo),
+            ChordState::Complete
+        ));
+        assert_eq!(pending, vec![key('d'), key('d')]);
+
+        // Or be a binding of its own
+        pending.clear();
+        bindings.press(&mut pending, key('g'), BindingContext::Todo);
+        assert!(matches!(
+            bindings.press(&mut pending, key('s'), BindingContext::Todo),
+            ChordState::Complete
+        ));
+        assert_eq!(
+            bindings.resolve(&pending, BindingContext::Todo),
+            Some(Action::Save)
+        );
+
+        // Bindings for somewhere else don't cut a chord short
+        let keybindings: Keybindings =
+            ron::from_str(r#"(archive_todo: "d", hide_group: "d d")"#).unwrap();
+        let bindings = Bindings::new(&keybindings, &[]);
+        pending.clear();
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Group),
+            ChordState::Pending
+        ));
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Group),
+            ChordState::Complete
+        ));
+        assert_eq!(
+            bindings.resolve(&pending, BindingContext::Group),
+            Some(Action::HideGroup)
+        );
+        pending.clear();
+        assert!(matches!(
+            bindings.press(&mut pending, key('d'), BindingContext::Todo),
+            ChordState::Complete
+        ));
+        assert_eq!(
+            bindings.resolve(&pending, BindingContext::Todo),
+            Some(Action::ArchiveTodo)
+        );
+
+        // And anything else is dropped
+        pending.clear();
+        assert!(matches!(
+            bindings.press(&mut pending, key('@'), BindingContext::Todo),
+            ChordState::NoMatch
+        ));
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    navigation::PositionHierarchy,
+    selection,
+    settings::Settings,
+    todo_config::{Todo, TodoConfig},
+    workflow::status_of,
+};
+
+// Todos in some of the statuses, pulled out of the tree and listed under the groups they are in
+pub struct ListView {
+    pub statuses: Vec<String>,
+    pub selected: Option<u64>, // Id of the todo under the cursor, the first one if it's not listed
+}
+
+impl ListView {
+    pub fn new(statuses: Vec<String>, selected: Option<u64>) -> Self {
+        Self { statuses, selected }
+    }
+
+    // Group path and todo for everything listed, in tree order
+    pub fn entries<'a>(
+        &self,
+        config: &'a TodoConfig,
+        settings: &Settings,
+    ) -> Vec<(Vec<&'a str>, &'a Todo)> {
+        config
+            .todos()
+            .into_iter()
+            .filter(|(_, t)| {
+                status_of(t, &settings.statuses).is_some_and(|s| self.statuses.contains(&s.name))
+            })
+            .collect()
+    }
+
+    pub fn index(&self, entries: &[(Vec<&str>, &Todo)]) -> Option<usize> {
+        selection::index(self.selected, &ids(entries))
+    }
+
+    pub fn position(&self, config: &TodoConfig, settings: &Settings) -> Option<PositionHierarchy> {
+        let entries = self.entries(config, settings);
+        let (_, todo) = entries.get(self.index(&entries)?)?;
+        PositionHierarchy::find_id(config, todo.id)
+    }
+
+    pub fn select_index(&mut self, config: &TodoConfig, settings: &Settings, index: usize) {
+        let entries = self.entries(config, settings);
+        selection::select(&mut self.selected, &ids(&entries), index);
+    }
+
+    pub fn cursor_up(&mut self, config: &TodoConfig, settings: &Settings) {
+        let ids = ids(&self.entries(config, settings));
+        selection::up(&mut self.selected, &ids);
+    }
+
+    pub fn cursor_down(&mut self, config: &TodoConfig, settings: &Settings) {
+        let ids = ids(&self.entries(config, settings));
+        selection::down(&mut self.selected, &ids);
+    }
+
+    // To the first todo of the group before
+    pub fn group_up(&mut self, config: &TodoConfig, settings: &Settings) {
+        let entries = self.entries(config, settings);
+        selection::run_up(&mut self.selected, &ids(&entries), |i| {
+            entries[i - 1].0 != entries[i].0
+        });
+    }
+
+    // To the first todo of the group after
+    pub fn group_down(&mut self, config: &TodoConfig, settings: &Settings) {
+        let entries = self.entries(config, settings);
+        selection::run_down(&mut self.selected, &ids(&entries), |i| {
+            entries[i - 1].0 != entries[i].0
+        });
+    }
+}
+
+fn ids(entries: &[(Vec<&str>, &Todo)]) -> Vec<u64> {
+    entries.iter().map(|(_, t)| t.id).collect()
+}
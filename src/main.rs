@@ -1,104 +1,144 @@
+pub mod action;
+pub mod agenda_view;
+pub mod board_view;
+pub mod calendar_view;
 pub mod command_manager;
+pub mod custom_commands;
+pub mod dates;
+pub mod debug;
+pub mod display;
+pub mod frame;
+pub mod hooks;
+pub mod keybindings;
+pub mod list_view;
+pub mod migration;
 pub mod navigation;
+pub mod palette;
+pub mod prompt;
+pub mod recovery;
+pub mod selection;
+pub mod settings;
+pub mod status;
+pub mod terminal;
+pub mod theme;
 pub mod todo_config;
+pub mod view_state;
+pub mod workflow;
 
 use std::{
+    collections::{BTreeMap, VecDeque},
     env,
     io::{stdout, Stdout, Write},
-    path::PathBuf,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
 use anyhow::{anyhow, Result};
 use crossterm::{
-    cursor::{Hide, MoveTo, Show},
-    event::{
-        poll, read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers,
-        MouseButton, MouseEventKind,
-    },
+    cursor::MoveTo,
+    event::{poll, read, Event, MouseButton, MouseEventKind},
     execute, queue,
     style::{Color, Print, ResetColor, SetForegroundColor},
-    terminal::{
-        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
-        LeaveAlternateScreen,
-    },
+    terminal::{Clear, ClearType},
 };
-use time::{format_description, OffsetDateTime};
-use time_humanize::HumanTime;
-use todo_config::Todo;
+use time::{Date, OffsetDateTime};
 
 use crate::{
-    navigation::{Cursor, HierarchyItemEnum, HierarchyItemEnumMut, PositionHierarchy},
-    todo_config::{num_to_str, Group, TodoConfig},
+    action::{create_top_group, cursor_context, dispatch, Action, Outcome},
+    agenda_view::{AgendaView, Section},
+    board_view::BoardView,
+    calendar_view::{day_of, CalendarView},
+    custom_commands::RunningCommand,
+    dates::now,
+    debug::DebugInfo,
+    display::{due_tier, split_tags, truncate, Column, DateDisplay},
+    frame::{Frame, Renderer},
+    hooks::{HookRunner, TodoSnapshot},
+    keybindings::{BindingContext, ChordState, Conflict, KeyPress},
+    list_view::ListView,
+    navigation::Cursor,
+    prompt::{PrefilledInput, ScriptedInput, TerminalInput},
+    settings::Settings,
+    status::StatusLine,
+    terminal::TerminalGuard,
+    theme::{Style, Theme},
+    todo_config::{num_to_str, Group, Priority, Todo, TodoConfig},
+    view_state::ViewState,
 };
 
-fn format_hierarchy(context: &TodoConfig, stdout: &mut Stdout) {
-    let mut out = stdout;
-    for group in context.groups.iter() {
+// Room kept right of the summary so the bars line up whether or not a group has overdue todos
+const OVERDUE_WIDTH: usize = 12;
+// Shortest a name gets cut to before columns and summaries are dropped instead
+const MIN_NAME_WIDTH: usize = 12;
+
+// Draws the groups as top level ones, just the zoomed in one while zoomed
+fn format_hierarchy(groups: &[Group], frame: &mut Frame, settings: &Settings, theme: &Theme) {
+    let width = frame.width();
+    let mut out = (frame, settings, theme, width);
+    for group in groups.iter() {
         out = group.traverse(
             out,
-            |g, d, v| {
-                queue!(v, Print("  ".repeat(d)), Print("["),).ok();
+            |g, d, (v, settings, theme, width)| {
+                v.print(format!("{}[", "  ".repeat(d)), Style::PLAIN);
                 if g.open {
-                    queue!(v, Print('*')).ok();
+                    v.print('*', Style::PLAIN);
+                } else if g.todo_count() == 0 {
+                    v.print(num_to_str(0), theme.empty_group);
                 } else {
-                    if g.todo_count() == 0 {
-                        queue!(v, SetForegroundColor(Color::DarkGrey)).ok();
-                    }
-                    queue!(v, Print(num_to_str(g.todo_count()))).ok();
-                    queue!(v, ResetColor).ok();
-                }
-                queue!(v, Print("] "), Print(&g.name), Print("\r\n")).ok();
-                (g.open, v)
-            },
-            |t, d, v| {
-                let format_time = format_description::parse("[year]-[month]-[day] [hour]:[minute]")
-                    .expect("Format to parse.");
-
-                if t.done_time.is_some() {
-                    queue!(v, SetForegroundColor(Color::DarkGrey)).ok();
-                } else if let Some(due) = t.due {
-                    if let Ok(now) = OffsetDateTime::now_local() {
-                        if now > due {
-                            queue!(v, SetForegroundColor(Color::Red)).ok();
-                        } else if (due - now).whole_hours() < 24 {
-                            queue!(v, SetForegroundColor(Color::Yellow)).ok();
-                        }
-                    }
+                    v.print(num_to_str(g.todo_count()), Style::PLAIN);
                 }
+                v.print("] ", Style::PLAIN);
+
+                let now = now();
+                let summary = &settings.group_summary;
+                let (progress, overdue) = summary.render(g.progress(now));
+                let reserved = if summary.overdue { OVERDUE_WIDTH } else { 0 };
+                let room = width.saturating_sub(d * 2 + 4);
+
+                // The name gives way to the summary, unless that would leave next to nothing of it
+                let column = progress.len() + reserved + 1;
+                let show_summary =
+                    !(progress.is_empty() && overdue.is_empty()) && room >= column + MIN_NAME_WIDTH;
+                let name_width = if show_summary { room - column } else { room };
 
-                queue!(
+                let dates = &settings.dates;
+                let due = match g.due.and_then(|due| dates.render(dates.due, due, now)) {
+                    Some(text) => truncate(&format!(" (due {})", text), name_width),
+                    None => String::new(),
+                };
+                // Nothing left to do means nothing to warn about
+                let due_style = match g.due {
+                    Some(due) if g.todo_count() > 0 => {
+                        due_style(due, now, settings, theme).unwrap_or(theme.summary)
+                    }
+                    _ => theme.summary,
+                };
+                print_name(
                     v,
-                    Print("  ".repeat(d)),
-                    Print("["),
-                    Print(if t.done_time.is_some() { "*" } else { " " }),
-                    Print("] "),
-                    Print(&t.name),
-                )
-                .ok();
+                    &truncate(&g.name, name_width - due.chars().count()),
+                    theme.group(d - 1),
+                    theme.tag,
+                );
+                v.print(due, due_style);
 
-                if let Some(due) = t.due {
-                    if let Ok(now) = OffsetDateTime::now_local() {
-                        queue!(
-                            v,
-                            Print(format!(
-                                " ({})",
-                                HumanTime::from_seconds((due - now).whole_seconds())
-                            ))
-                        )
-                        .ok();
-                    } else {
-                        queue!(
-                            v,
-                            Print(format!(" ({})", due.format(&format_time).unwrap()))
-                        )
-                        .ok();
+                if show_summary {
+                    if !progress.is_empty() {
+                        v.move_to_column(width - column + 1);
+                        v.print(progress, theme.summary);
+                    }
+                    if !overdue.is_empty() {
+                        v.move_to_column(width - reserved);
+                        v.print(truncate(&overdue, reserved), theme.overdue);
                     }
                 }
-                queue!(v, Print("\r\n")).ok();
-
-                queue!(v, SetForegroundColor(Color::Reset)).ok();
 
-                v
+                v.newline();
+                (g.open, (v, settings, theme, width))
+            },
+            |t, d, (v, settings, theme, width)| {
+                print_todo(v, t, d, "", settings, theme);
+                (v, settings, theme, width)
             },
             |_, _, v| v,
             1,
@@ -106,673 +146,998 @@ fn format_hierarchy(context: &TodoConfig, stdout: &mut Stdout) {
     }
 }
 
-fn draw_vis(stdout: &mut Stdout, config: &TodoConfig, cursor: &Cursor) -> Result<()> {
-    match cursor {
-        Cursor::Hierarchy(h) => {
-            queue!(
-                stdout,
-                Clear(crossterm::terminal::ClearType::All),
-                MoveTo(0, 0),
-                Print(format!("{:?}\n\r", h.indexes))
-            )?;
-
-            format_hierarchy(config, stdout);
-
-            let cursor_y: u16 = h.vert_pos(config)?.try_into()?;
-
-            queue!(stdout, MoveTo(0, cursor_y + 1), Print("> "))?;
+// The todos of a list view under headings for their groups, with the cursor
+fn format_list(
+    list: &ListView,
+    context: &TodoConfig,
+    frame: &mut Frame,
+    settings: &Settings,
+    theme: &Theme,
+) {
+    let entries = list.entries(context, settings);
+    if entries.is_empty() {
+        frame.print(
+            format!("  Nothing is {}", list.statuses.join(" or ")),
+            theme.summary,
+        );
+        return;
+    }
 
-            stdout.flush()?;
+    let selected = list.index(&entries);
+    let mut heading: Option<&Vec<&str>> = None;
+    let mut rows = 0;
+    let mut cursor_y = None;
+    for (i, (path, todo)) in entries.iter().enumerate() {
+        if heading != Some(path) {
+            frame.print(
+                format!("  {}", truncate(&path.join(" > "), frame.width() - 2)),
+                theme.group(0),
+            );
+            frame.newline();
+            rows += 1;
+            heading = Some(path);
         }
+        if selected == Some(i) {
+            cursor_y = Some(rows);
+        }
+        print_todo(frame, todo, 1, "", settings, theme);
+        rows += 1;
     }
 
-    Ok(())
+    if let Some(y) = cursor_y {
+        frame.move_to(0, y + 1);
+        frame.print("> ", theme.cursor);
+    }
 }
 
-fn prompt(stdout: &mut Stdout, prompt: &str, def: &str) -> Result<String> {
-    // disable_raw_mode()?;
-    execute!(stdout, Show)?;
+// A column for each status with its cards under it, cut off at the bottom of the screen
+fn format_board(
+    board: &BoardView,
+    context: &TodoConfig,
+    frame: &mut Frame,
+    settings: &Settings,
+    theme: &Theme,
+) {
+    let now = now();
+    let columns = board_view::columns(settings);
+    let cards = board.cards(context, settings);
+    let selected = board.index(&cards);
+    let column_width = frame.width() / columns.len().max(1);
 
-    execute!(
-        stdout,
-        MoveTo(0, 0),
-        Clear(ClearType::CurrentLine),
-        Print(prompt)
-    )?;
+    for (c, (status, cards)) in columns.iter().zip(cards.iter()).enumerate() {
+        let x = c * column_width;
+        frame.move_to(x, 1);
+        frame.print(
+            truncate(
+                &format!("  {} ({})", status.name, cards.len()),
+                column_width.saturating_sub(1),
+            ),
+            // Marks the column even when it has no cards to put the cursor on
+            if c == board.column {
+                theme.status_bar
+            } else {
+                theme.group(0)
+            },
+        );
+
+        for (i, todo) in cards.iter().enumerate() {
+            frame.move_to(x, i + 2);
+            if c == board.column && selected == Some(i) {
+                frame.print("> ", theme.cursor);
+            } else {
+                frame.print("  ", Style::PLAIN);
+            }
+            let name = truncate(&todo.name, column_width.saturating_sub(3));
+            let style = todo_style(todo, now, settings, theme);
+            let tag = if todo.done_time.is_some() || todo.is_deferred(now) {
+                style
+            } else {
+                theme.tag
+            };
+            print_name(frame, &name, style, tag);
+        }
+    }
+}
 
-    let mut out = def.to_string();
-    let mut cursor_pos = out.len();
+// A month of days with how much is due on each and the picked day's todos below, or a week with
+// the todos under each day
+fn format_calendar(
+    calendar: &CalendarView,
+    context: &TodoConfig,
+    frame: &mut Frame,
+    settings: &Settings,
+    theme: &Theme,
+) {
+    let now = now();
+    let mut due: BTreeMap<Date, Vec<&Todo>> = BTreeMap::new();
+    for (_, todo) in context.todos() {
+        if let Some(date) = todo.due {
+            due.entry(day_of(date, now)).or_default().push(todo);
+        }
+    }
 
-    let mut done = false;
+    let days = calendar.days();
+    let cell_width = frame.width() / 7;
+    // Picked, then overdue, today and days outside the month
+    let day_style = |day: Date| {
+        let todos = due.get(&day).map_or(&[][..], |todos| todos.as_slice());
+        if day == calendar.day {
+            theme.status_bar
+        } else if day < now.date() && todos.iter().any(|t| t.done_time.is_none()) {
+            theme.overdue
+        } else if day == now.date() {
+            theme.cursor
+        } else if !calendar.week && day.month() != calendar.day.month() {
+            theme.done
+        } else {
+            theme.todo
+        }
+    };
 
-    while !done {
-        execute!(
-            stdout,
-            MoveTo(
-                prompt
-                    .len()
-                    .try_into()
-                    .expect("Prompt should be less than 64Ki bytes."),
-                0
-            ),
-            Clear(ClearType::UntilNewLine),
-            Print(&out),
-            MoveTo(
-                (prompt.len() + cursor_pos)
-                    .try_into()
-                    .expect("Prompt should be less than 64Ki bytes."),
-                0
-            ),
-        )?;
+    if calendar.week {
+        let todos = CalendarView::todos_on(context, calendar.day);
+        let selected = calendar.index(&todos);
+        for &day in &days {
+            let x = day.weekday().number_days_from_monday() as usize * cell_width;
+            frame.move_to(x, 1);
+            frame.print(
+                truncate(
+                    &format!(" {} {}", &day.weekday().to_string()[..3], day.day()),
+                    cell_width.saturating_sub(1),
+                ),
+                day_style(day),
+            );
 
-        let evt = read()?;
-        if let Event::Key(ke) = evt {
-            if let KeyCode::Char(c) = ke.code {
-                out.insert(cursor_pos, c);
-                cursor_pos += 1;
-            } else if let KeyCode::Backspace = ke.code {
-                if cursor_pos > 0 {
-                    out.remove(cursor_pos - 1);
-                    cursor_pos -= 1;
-                }
-            } else if let KeyCode::Left = ke.code {
-                cursor_pos = cursor_pos.saturating_sub(1);
-            } else if let KeyCode::Right = ke.code {
-                if cursor_pos < out.len() {
-                    cursor_pos += 1;
+            for (row, todo) in due.get(&day).into_iter().flatten().enumerate() {
+                frame.move_to(x, row + 2);
+                if day == calendar.day && selected.map(|i| todos[i].1.id) == Some(todo.id) {
+                    frame.print("> ", theme.cursor);
+                } else {
+                    frame.print("  ", Style::PLAIN);
                 }
-            } else if let KeyCode::Home = ke.code {
-                cursor_pos = 0;
-            } else if let KeyCode::End = ke.code {
-                cursor_pos = out.len();
-            } else if let KeyCode::Enter = ke.code {
-                done = true;
+                frame.print(
+                    truncate(&todo.name, cell_width.saturating_sub(3)),
+                    todo_style(todo, now, settings, theme),
+                );
             }
         }
+        return;
     }
 
-    execute!(stdout, Hide)?;
-    // enable_raw_mode()?;
+    frame.move_to(0, 1);
+    frame.print(
+        format!("  {} {}", calendar.day.month(), calendar.day.year()),
+        theme.group(0),
+    );
+    for day in days.iter().take(7) {
+        frame.move_to(
+            day.weekday().number_days_from_monday() as usize * cell_width,
+            2,
+        );
+        frame.print(
+            format!(" {}", &day.weekday().to_string()[..3]),
+            theme.summary,
+        );
+    }
+    // Placed by weekday, the first week can be cut short at the start of the calendar
+    let skip = days
+        .first()
+        .map_or(0, |day| day.weekday().number_days_from_monday() as usize);
+    for (i, &day) in days.iter().enumerate() {
+        let i = i + skip;
+        frame.move_to((i % 7) * cell_width, i / 7 + 3);
+        let count = due.get(&day).map_or(0, Vec::len);
+        let text = if count > 0 {
+            format!(" {:>2} ({})", day.day(), count)
+        } else {
+            format!(" {:>2}", day.day())
+        };
+        frame.print(
+            truncate(&text, cell_width.saturating_sub(1)),
+            day_style(day),
+        );
+    }
 
-    Ok(out)
+    let top = days.len() / 7 + 4;
+    frame.move_to(0, top);
+    frame.print(
+        format!(
+            "  {} {} {}",
+            calendar.day.weekday(),
+            calendar.day.day(),
+            calendar.day.month()
+        ),
+        theme.group(0),
+    );
+    frame.newline();
+
+    let todos = CalendarView::todos_on(context, calendar.day);
+    if todos.is_empty() {
+        frame.print("  Nothing is due", theme.summary);
+        return;
+    }
+    for (path, todo) in todos.iter() {
+        print_todo(frame, todo, 1, &path.join(" > "), settings, theme);
+    }
+    if let Some(i) = calendar.index(&todos) {
+        frame.move_to(0, top + 1 + i);
+        frame.print("> ", theme.cursor);
+    }
 }
 
-fn prompt_date(stdout: &mut Stdout) -> Option<OffsetDateTime> {
-    if prompt(stdout, "Add a due date? (y/n) ", "").ok()? == "y" {
-        let mut current = OffsetDateTime::now_local().ok()?;
+// Each section of the agenda with its todos and the groups they are in
+fn format_agenda(
+    agenda: &AgendaView,
+    context: &TodoConfig,
+    frame: &mut Frame,
+    settings: &Settings,
+    theme: &Theme,
+) {
+    let entries = AgendaView::entries(context);
+    if entries.is_empty() {
+        frame.print("  Nothing is due soon", theme.summary);
+        return;
+    }
 
-        let year_input = prompt(stdout, "Year?  ", &format!("{}", current.year())).ok()?;
-        if !year_input.is_empty() {
-            current = current.replace_year(year_input.parse().ok()?).ok()?;
+    let selected = agenda.index(&entries);
+    let mut heading = None;
+    let mut rows = 0;
+    let mut cursor_y = None;
+    for (i, (section, path, todo)) in entries.iter().enumerate() {
+        if heading != Some(section) {
+            let style = if *section == Section::Overdue {
+                theme.overdue
+            } else {
+                theme.group(0)
+            };
+            frame.print(format!("  {}", section), style);
+            frame.newline();
+            rows += 1;
+            heading = Some(section);
+        }
+        if selected == Some(i) {
+            cursor_y = Some(rows);
         }
-        let month_input = prompt(stdout, "Month? ", &format!("{}", current.month())).ok()?;
-        if !month_input.is_empty() {
-            current = current.replace_month(month_input.parse().ok()?).ok()?;
+        print_todo(frame, todo, 1, &path.join(" > "), settings, theme);
+        rows += 1;
+    }
+
+    if let Some(y) = cursor_y {
+        frame.move_to(0, y + 1);
+        frame.print("> ", theme.cursor);
+    }
+}
+
+// A todo row indented to depth, used by every view
+fn print_todo(
+    v: &mut Frame,
+    t: &Todo,
+    d: usize,
+    breadcrumb: &str, // Groups the todo is in, for views that don't show them around it
+    settings: &Settings,
+    theme: &Theme,
+) {
+    let width = v.width();
+    let now = now();
+
+    let deferred = t.is_deferred(now);
+    let style = todo_style(t, now, settings, theme);
+
+    v.print(
+        format!(
+            "{}[{}] ",
+            "  ".repeat(d),
+            workflow::glyph(t, &settings.statuses)
+        ),
+        style,
+    );
+    let mut room = width.saturating_sub(d * 2 + 4);
+    if t.priority == Priority::High && t.done_time.is_none() {
+        v.print("! ", theme.overdue);
+        room = room.saturating_sub(2);
+    }
+
+    // Columns that don't fit beside a readable name are left off, from the right
+    let mut columns = vec![];
+    let mut columns_width = 0;
+    for &column in settings.columns.iter() {
+        if room < columns_width + column.width() + 1 + MIN_NAME_WIDTH {
+            break;
         }
-        let day_input = prompt(stdout, "Day? ", &format!("{}", current.day())).ok()?;
-        if !day_input.is_empty() {
-            current = current.replace_day(day_input.parse().ok()?).ok()?;
+        columns_width += column.width() + 1;
+        columns.push(column);
+    }
+
+    let dates = &settings.dates;
+    let mut name = t.name.clone();
+    let mut suffix = String::new();
+    if settings.columns.is_empty() {
+        if let Some(due) = t.due.and_then(|due| dates.render(dates.due, due, now)) {
+            suffix.push_str(&format!(" ({})", due));
         }
-        let hour_input = prompt(stdout, "Hour? ", &format!("{}", current.hour())).ok()?;
-        if !hour_input.is_empty() {
-            current = current.replace_hour(hour_input.parse().ok()?).ok()?;
+        if let Some(done) = t
+            .done_time
+            .and_then(|done| dates.render(dates.done, done, now))
+        {
+            suffix.push_str(&format!(" (done {})", done));
         }
-        let minute_input = prompt(stdout, "Minute? ", &format!("{}", current.minute())).ok()?;
-        if !minute_input.is_empty() {
-            current = current.replace_minute(minute_input.parse().ok()?).ok()?;
+    } else if settings.columns.contains(&Column::Tags) {
+        name = split_tags(&t.name).0;
+    }
+    if deferred {
+        if let Some(until) = t
+            .defer_until
+            .and_then(|until| dates.render(DateDisplay::Relative, until, now))
+        {
+            suffix.push_str(&format!(" (snoozed, back {})", until));
         }
+    }
+
+    // Tags keep the done look so finished and snoozed todos stay quiet
+    let tag = if t.done_time.is_some() || deferred {
+        style
+    } else {
+        theme.tag
+    };
+    let name_width = room.saturating_sub(columns_width);
+    let suffix = truncate(&suffix, name_width);
+    let trail = if breadcrumb.is_empty() {
+        String::new()
+    } else {
+        let room = (name_width - suffix.chars().count()).saturating_sub(MIN_NAME_WIDTH);
+        truncate(&format!(" ({})", breadcrumb), room)
+    };
+    let name = truncate(
+        &name,
+        name_width - suffix.chars().count() - trail.chars().count(),
+    );
+    print_name(v, &name, style, tag);
+    v.print(suffix, style);
+    v.print(trail, theme.summary);
+
+    let mut x = width - columns_width;
+    for column in columns {
+        let (text, column_style) = match column {
+            Column::Due => (
+                t.due
+                    .and_then(|due| dates.render(dates.due, due, now))
+                    .unwrap_or_default(),
+                style,
+            ),
+            Column::Created => (
+                dates
+                    .render(DateDisplay::Relative, t.created, now)
+                    .unwrap_or_default(),
+                theme.summary,
+            ),
+            Column::Completed => (
+                t.done_time
+                    .and_then(|done| dates.render(DateDisplay::Relative, done, now))
+                    .unwrap_or_default(),
+                theme.summary,
+            ),
+            Column::Tags => (split_tags(&t.name).1.join(" "), tag),
+        };
+
+        let text = truncate(&text, column.width());
+        let pad = column.width() - text.chars().count();
+        v.move_to_column(x + 1 + pad);
+        v.print(text, column_style);
+        x += column.width() + 1;
+    }
+    v.newline();
+}
+
+// Done, snoozed, or how soon it's due
+fn todo_style(t: &Todo, now: OffsetDateTime, settings: &Settings, theme: &Theme) -> Style {
+    if t.done_time.is_some() {
+        theme.done
+    } else if t.is_deferred(now) {
+        theme.deferred
+    } else {
+        t.due
+            .and_then(|due| due_style(due, now, settings, theme))
+            .unwrap_or(theme.todo)
+    }
+}
 
-        Some(current)
+// Overdue or the style of the first due tier it falls in
+fn due_style(
+    due: OffsetDateTime,
+    now: OffsetDateTime,
+    settings: &Settings,
+    theme: &Theme,
+) -> Option<Style> {
+    if now > due {
+        Some(theme.overdue)
     } else {
-        None
+        due_tier(&settings.due_tiers, due, now).map(|tier| theme.due_tier(tier))
     }
 }
 
-fn prompt_date_in_place(
+// Prints a name with its #tags picked out
+fn print_name(frame: &mut Frame, name: &str, style: Style, tag: Style) {
+    for (i, word) in name.split(' ').enumerate() {
+        if i > 0 {
+            frame.print(' ', style);
+        }
+        if word.len() > 1 && word.starts_with('#') {
+            frame.print(word, tag);
+        } else {
+            frame.print(word, style);
+        }
+    }
+}
+
+fn draw_vis(
     stdout: &mut Stdout,
-    mut current: OffsetDateTime,
-) -> Option<OffsetDateTime> {
-    let choice = prompt(stdout, "Change due date? (k/y/n) ", "").ok()?;
-    match choice.as_str() {
-        "y" => {
-            let year_input = prompt(stdout, "Year? ", &format!("{}", current.year())).ok()?;
-            if !year_input.is_empty() {
-                current = current.replace_year(year_input.parse().ok()?).ok()?;
-            }
-            let month_input = prompt(stdout, "Month? ", &format!("{}", current.month())).ok()?;
-            if !month_input.is_empty() {
-                current = current.replace_month(month_input.parse().ok()?).ok()?;
-            }
-            let day_input = prompt(stdout, "Day? ", &format!("{}", current.day())).ok()?;
-            if !day_input.is_empty() {
-                current = current.replace_day(day_input.parse().ok()?).ok()?;
-            }
-            let hour_input = prompt(stdout, "Hour? ", &format!("{}", current.hour())).ok()?;
-            if !hour_input.is_empty() {
-                current = current.replace_hour(hour_input.parse().ok()?).ok()?;
-            }
-            let minute_input = prompt(stdout, "Minute? ", &format!("{}", current.minute())).ok()?;
-            if !minute_input.is_empty() {
-                current = current.replace_minute(minute_input.parse().ok()?).ok()?;
-            }
+    renderer: &mut Renderer,
+    config: &TodoConfig,
+    cursor: &Cursor,
+    status: &mut StatusLine,
+    settings: &Settings,
+    theme: &Theme,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut frame = Frame::for_terminal();
+
+    match cursor {
+        Cursor::Hierarchy(h) => {
+            frame.move_to(0, 1);
+            format_hierarchy(h.top(config), &mut frame, settings, theme);
 
-            Some(current)
+            let cursor_y = h.vert_pos(config)?;
+            frame.move_to(0, cursor_y + 1);
+            frame.print("> ", theme.cursor);
+        }
+        Cursor::List(list) => {
+            frame.move_to(0, 1);
+            format_list(list, config, &mut frame, settings, theme);
+        }
+        Cursor::Board(board) => format_board(board, config, &mut frame, settings, theme),
+        Cursor::Calendar(calendar) => {
+            format_calendar(calendar, config, &mut frame, settings, theme)
         }
-        "n" => None,
-        _ => {
-            // Keep
-            Some(current)
+        Cursor::Agenda(agenda) => {
+            frame.move_to(0, 1);
+            format_agenda(agenda, config, &mut frame, settings, theme);
         }
     }
+
+    status.draw(&mut frame, config, cursor, settings, theme);
+    renderer.render(stdout, frame)?;
+
+    if let Some(debug) = &mut status.debug {
+        debug.render_time = started.elapsed();
+    }
+    Ok(())
 }
 
-fn create_top_group(config: &mut TodoConfig, stdout: &mut Stdout) -> Result<()> {
-    let name = prompt(stdout, "Enter Name for Top Group: ", "")?;
+fn show_conflicts(stdout: &mut Stdout, conflicts: &[Conflict], settings_path: &Path) -> Result<()> {
+    queue!(
+        stdout,
+        MoveTo(0, 0),
+        Clear(ClearType::All),
+        SetForegroundColor(Color::Yellow),
+        Print(format!(
+            "Some keybindings in {} shadow each other:\r\n\r\n",
+            settings_path.display()
+        )),
+        ResetColor
+    )?;
+    for conflict in conflicts {
+        queue!(stdout, Print(format!("  {}\r\n", conflict)))?;
+    }
+    queue!(stdout, Print("\r\nPress any key to continue"))?;
+    stdout.flush()?;
 
-    config.groups.push(Group {
-        hidden: false,
-        name,
-        open: false,
-        todos: vec![],
-        completed: vec![],
-        todo_archive: vec![],
-        subgroups: vec![],
-        subgroup_archive: vec![],
-    });
+    while !(matches!(read()?, Event::Key(_))) {}
 
     Ok(())
 }
 
-fn activate_item(cursor: &mut Cursor, config: &mut TodoConfig) -> Result<()> {
-    if match cursor {
-        Cursor::Hierarchy(ref mut h) => {
-            matches!(h.find_item(config)?.item, HierarchyItemEnum::Group(_))
-        }
-    } {
-        match cursor {
-            Cursor::Hierarchy(ref mut h) => {
-                if let HierarchyItemEnumMut::Group(g) = h.find_item_mut(config)?.item {
-                    g.open = !g.open;
-                }
-            }
-        };
-    } else if match cursor {
-        Cursor::Hierarchy(ref mut h) => {
-            matches!(h.find_item(config)?.item, HierarchyItemEnum::Todo(_))
-        }
-    } {
-        match cursor {
-            Cursor::Hierarchy(ref mut h) => {
-                let g = h.find_group_mut(config)?;
-                if h.last()? < g.subgroups.len() + g.todos.len() {
-                    let mut t = g.todos.remove(h.last()? - g.subgroups.len());
-                    t.done_time = OffsetDateTime::now_local().ok();
-                    g.completed.push(t);
-                } else if h.last()? < g.subgroups.len() + g.todos.len() + g.completed.len() {
-                    let mut t = g
-                        .completed
-                        .remove(h.last()? - g.subgroups.len() - g.todos.len());
-                    t.done_time = None;
-                    g.todos.push(t);
-                }
-            }
-        };
+// Writes the view state, and the data only if it changed since the last write
+fn save(
+    config: &TodoConfig,
+    cursor: &Cursor,
+    config_path: &PathBuf,
+    saved: &mut String,
+    hooks: &mut HookRunner,
+    settings: &Settings,
+) -> Result<()> {
+    ViewState::capture(config, cursor, settings).write_state(&ViewState::path_for(config_path))?;
+
+    let current = config.to_ron()?;
+    if current != *saved {
+        config.write_config(config_path)?;
+        *saved = current;
+        hooks.saved();
+    }
+
+    Ok(())
+}
+
+// Runs one action on the item under the saved cursor, answering its prompts from the arguments
+fn run_command(
+    command: &[String],
+    config: &mut TodoConfig,
+    cursor: &mut Cursor,
+    config_path: &PathBuf,
+    saved: &mut String,
+    hooks: &mut HookRunner,
+    settings: &Settings,
+) -> Result<()> {
+    let (name, answers) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("Usage: dmc-todo [path] do <action> [answers...]"))?;
+    let action = Action::from_name(name).ok_or_else(|| anyhow!("Unknown action {}", name))?;
+    // dispatch skips it quietly, which here would look like it worked
+    let context = action.context();
+    if context != BindingContext::Global && context != cursor_context(config, cursor, settings) {
+        return Err(anyhow!(
+            "{} only works {}, and the saved cursor isn't on one",
+            name,
+            context
+        ));
+    }
+
+    let before = TodoSnapshot::take(config);
+    let outcome = dispatch(
+        action,
+        config,
+        cursor,
+        &mut ScriptedInput::new(answers.iter().cloned()),
+        settings,
+    )?;
+    hooks.after_action(&before, config);
+
+    if outcome != Outcome::QuitWithoutSaving {
+        save(config, cursor, config_path, saved, hooks, settings)?;
     }
 
     Ok(())
 }
 
 fn main() -> Result<()> {
-    let custom_path = &env::args().nth(1);
+    // dmc-todo [--debug] [path] [do <action> [answers...]]
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Only before `do`, anything after it is an answer
+    let debug = match args.iter().position(|a| a == "--debug") {
+        Some(i) if !args[..i].contains(&"do".to_string()) => {
+            args.remove(i);
+            true
+        }
+        _ => false,
+    };
+    let (custom_path, command) = match args.iter().position(|a| a == "do") {
+        Some(i) => (args[..i].first(), Some(&args[i + 1..])),
+        None => (args.first(), None),
+    };
 
     let config_path = &if let Some(path) = custom_path {
         PathBuf::from(path)
     } else {
         dirs::config_dir().unwrap().join("dmc/todo/config.ron")
     };
+    let settings_path = &Settings::path_for(config_path);
 
-    let mut config = match TodoConfig::read_config(config_path) {
-        Ok(config) => {
-            println!("Config read successfully");
-            config
+    let mut config = loop {
+        match TodoConfig::read_config(config_path, settings_path) {
+            Ok(config) => {
+                println!("Config read successfully");
+                break config;
+            }
+            Err(err) => match err {
+                todo_config::ConfigError::NoConfigFile => {
+                    println!("Config not found, creating default config");
+                    let config = TodoConfig::default();
+                    config.write_config(config_path)?;
+                    break config;
+                }
+                todo_config::ConfigError::Io(_) => {
+                    return Err(anyhow!("Error loading config file."));
+                }
+                todo_config::ConfigError::Parse(err) => {
+                    if !recovery::recover_from_parse_error(config_path, &err)? {
+                        return Err(anyhow!("Error parsing config file."));
+                    }
+                }
+                err @ todo_config::ConfigError::TooNew { .. } => {
+                    return Err(anyhow!("{}. Refusing to load it.", err));
+                }
+                err @ todo_config::ConfigError::Migration { .. } => {
+                    return Err(anyhow!("{}. The file was left as it was.", err));
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Generic error loading config file this should not be possible."
+                    ));
+                }
+            },
         }
-        Err(err) => match err {
-            todo_config::ConfigError::NoConfigFile => {
-                println!("Config not found, creating default config");
-                let config = TodoConfig::default();
-                config.write_config(config_path)?;
-                config
+    };
+
+    let settings = loop {
+        match Settings::read_settings(settings_path) {
+            Ok(settings) => break settings,
+            Err(todo_config::ConfigError::NoConfigFile) => {
+                let settings = Settings::default();
+                settings.write_settings(settings_path)?;
+                break settings;
+            }
+            Err(todo_config::ConfigError::Parse(err)) => {
+                if !recovery::recover_from_parse_error(settings_path, &err)? {
+                    return Err(anyhow!("Error parsing settings file."));
+                }
             }
-            todo_config::ConfigError::Io(_) => {
-                return Err(anyhow!("Error loading config file."));
+            Err(err @ todo_config::ConfigError::TooNew { .. }) => {
+                return Err(anyhow!("{}. Refusing to load it.", err));
             }
-            todo_config::ConfigError::Parse(_) => {
-                return Err(anyhow!("Error parsing config file."));
+            Err(err @ todo_config::ConfigError::Migration { .. }) => {
+                return Err(anyhow!("{}. The file was left as it was.", err));
             }
-            _ => {
-                return Err(anyhow!(
-                    "Generic error loading config file this should not be possible."
-                ));
+            Err(_) => {
+                return Err(anyhow!("Error loading settings file."));
             }
-        },
+        }
     };
 
-    let mut cursor = Cursor::Hierarchy(PositionHierarchy::new());
-    // let mut cursor_flat = PositionFlat::new();
+    let state_path = &ViewState::path_for(config_path);
+    let mut cursor = ViewState::restore(state_path, &mut config);
+    let mut saved = config.to_ron()?;
+    let mut hooks = HookRunner::new(settings.hooks.clone(), config_path);
+
+    if let Some(command) = command {
+        let result = run_command(
+            command,
+            &mut config,
+            &mut cursor,
+            config_path,
+            &mut saved,
+            &mut hooks,
+            &settings,
+        );
+        hooks.finish();
+        return result;
+    }
 
-    enable_raw_mode()?;
+    let mut status = StatusLine::new(config_path);
+    if debug {
+        status.debug = Some(DebugInfo::new(config_path));
+    }
 
+    let guard = TerminalGuard::enter()?;
     let mut stdout = stdout();
 
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, Hide)?;
+    // Caught so unsaved changes can be written somewhere before the app goes down
+    let result = panic::catch_unwind(AssertUnwindSafe(|| -> Result<()> {
+        let bindings = settings.bindings();
+        let theme = settings.theme.resolve();
+        let mut renderer = Renderer::default();
+        let conflicts = bindings.conflicts();
+        if !conflicts.is_empty() {
+            show_conflicts(&mut stdout, &conflicts, settings_path)?;
+        }
 
-    draw_vis(&mut stdout, &config, &cursor)?;
+        draw_vis(
+            &mut stdout,
+            &mut renderer,
+            &config,
+            &cursor,
+            &mut status,
+            &settings,
+            &theme,
+        )?;
 
-    loop {
-        // Wait up to 1s for another event
-        if poll(std::time::Duration::from_millis(1_000))? {
-            // Fixing blanks
-            if config.groups.is_empty() {
-                create_top_group(&mut config, &mut stdout)?;
-            }
+        let minute = || now().unix_timestamp() / 60;
+        let mut drawn_minute = minute();
+        let mut custom: Option<RunningCommand> = None; // Dropping it stops it
 
-            for group in config.groups.iter_mut() {
-                group.traverse_mut::<&time::Duration>(
-                    &config.archive_time,
-                    |g, _d, v| {
-                        for i in (0..g.completed.len()).rev() {
-                            if let Some(done_time) = g.completed[i].done_time {
-                                if let Ok(now) = OffsetDateTime::now_local() {
-                                    if now - done_time > *v {
-                                        g.todo_archive.push(g.completed.remove(i));
-                                    }
+        'main: loop {
+            hooks.tick(&config);
+
+            // A custom command that is done applies its update and shows what it printed
+            if let Some(mut running) = custom.take() {
+                let command = &settings.custom_commands[running.index];
+                match running.poll() {
+                    Ok(true) => custom = Some(running),
+                    done => {
+                        match done {
+                            Ok(_) => {
+                                let (output, changed) =
+                                    running.finish(&mut config, &mut cursor, &settings);
+                                status.dirty |= changed;
+                                if !output.trim().is_empty() {
+                                    custom_commands::show_output(
+                                        &mut stdout,
+                                        &command.name,
+                                        &output,
+                                    )?;
+                                    renderer.invalidate();
                                 }
                             }
+                            Err(e) => status.error(format!("{} failed: {}", command.name, e)),
                         }
-
-                        (true, v)
-                    },
-                    |_t, _d, v| v,
-                    |_g, _d, v| v,
-                    0,
-                );
+                        status.running = None;
+                        draw_vis(
+                            &mut stdout,
+                            &mut renderer,
+                            &config,
+                            &cursor,
+                            &mut status,
+                            &settings,
+                            &theme,
+                        )
+                        .ok();
+                    }
+                }
             }
 
-            let event = read()?;
-
-            match event {
-                Event::Key(ke) => {
-                    if ke.code == config.keybindings.quit
-                        && ke.modifiers.contains(KeyModifiers::ALT)
-                    {
-                        break;
-                    } else if ke.code == config.keybindings.quit {
-                        config.write_config(config_path)?;
-                        break;
-                    } else if ke.code == config.keybindings.save {
-                        config.write_config(config_path)?;
-                    } else if ke.code == config.keybindings.cursor_up {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => h.cursor_up(&config).ok(),
-                        };
-                    } else if ke.code == config.keybindings.cursor_down {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => h.cursor_down(&config).ok(),
-                        };
-                    } else if ke.code == config.keybindings.group_up {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => h.group_up(&config).ok(),
-                        };
-                    } else if ke.code == config.keybindings.group_down {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => h.group_down(&config).ok(),
-                        };
-                    } else if ke.code == config.keybindings.hierarchy_up {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => h.hierarchy_up(&config).ok(),
-                        };
-                    } else if ke.code == config.keybindings.hierarchy_down {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => h.hierarchy_down(&mut config).ok(),
-                        };
-                    } else if ke.code == config.keybindings.toggle_group
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                if let HierarchyItemEnumMut::Group(g) =
-                                    h.find_item_mut(&mut config)?.item
-                                {
-                                    g.open = !g.open;
-                                }
-                            }
-                        }
-                    } else if ke.code == config.keybindings.toggle_todo
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Todo(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                let g = h.find_group_mut(&mut config)?;
-                                if h.last()? < g.subgroups.len() + g.todos.len() {
-                                    let mut t = g.todos.remove(h.last()? - g.subgroups.len());
-                                    t.done_time = OffsetDateTime::now_local().ok();
-                                    g.completed.push(t);
-                                } else if h.last()?
-                                    < g.subgroups.len() + g.todos.len() + g.completed.len()
-                                {
-                                    let mut t = g
-                                        .completed
-                                        .remove(h.last()? - g.subgroups.len() - g.todos.len());
-                                    t.done_time = None;
-                                    g.todos.push(t);
-                                }
-                            }
-                        }
-                    } else if ke.code == config.keybindings.archive_todo
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Todo(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                let g = h.find_group_mut(&mut config)?;
-                                let t = if h.last()? < g.subgroups.len() + g.todos.len() {
-                                    g.todos.remove(h.last()? - g.subgroups.len())
-                                } else {
-                                    g.completed
-                                        .remove(h.last()? - g.subgroups.len() - g.todos.len())
-                                };
-                                g.todo_archive.push(t);
-
-                                if h.last()? >= g.len() {
-                                    if h.last()? > 0 {
-                                        *h.last_mut()? -= 1;
-                                    } else {
-                                        h.hierarchy_up(&config)?;
-                                    }
-                                }
-                            }
-                        }
-                    } else if ke.code == config.keybindings.hide_group
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                if h.indexes.len() == 1 {
-                                    let t = config.groups.remove(h.last()?);
-                                    config.archive_groups.push(t);
-
-                                    if h.last()? >= config.groups.len() && h.last()? > 0 {
-                                        *h.last_mut()? -= 1;
-                                    }
-                                } else {
-                                    let g = h.find_group_mut(&mut config)?;
-                                    let t = g.subgroups.remove(h.last()?);
-                                    g.subgroup_archive.push(t);
-                                    if h.last()? >= g.len() {
-                                        if h.last()? > 0 {
-                                            *h.last_mut()? -= 1;
-                                        } else {
-                                            h.hierarchy_up(&config)?;
-                                        }
+            // Wait up to 1s for another event, hooks get a tick either way. Sooner while a
+            // custom command runs so its output shows up when it's done.
+            let wait = if custom.is_some() { 100 } else { 1_000 };
+            if poll(std::time::Duration::from_millis(wait))? {
+                // Fixing blanks
+                if config.groups.is_empty() {
+                    match create_top_group(&mut config, &mut TerminalInput(&mut stdout)) {
+                        Ok(()) => status.dirty = true,
+                        Err(e) => status.error(format!("Couldn't add a group: {}", e)),
+                    }
+                    renderer.invalidate_row(0);
+                }
+
+                for group in config.groups.iter_mut() {
+                    let (_, archived) = group.traverse_mut::<(&time::Duration, bool)>(
+                        (&settings.archive_time, false),
+                        |g, _d, (archive_time, mut archived)| {
+                            for i in (0..g.completed.len()).rev() {
+                                if let Some(done_time) = g.completed[i].done_time {
+                                    if now() - done_time > *archive_time {
+                                        g.todo_archive.push(g.completed.remove(i));
+                                        archived = true;
                                     }
                                 }
                             }
-                        }
-                    } else if ke.code == config.keybindings.add_todo
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                if let HierarchyItemEnumMut::Group(g) =
-                                    h.find_item_mut(&mut config)?.item
-                                {
-                                    let todo_name = prompt(&mut stdout, "Todo: ", "")?;
-                                    g.todos.push(Todo {
-                                        name: todo_name,
-                                        done_time: None,
-                                        due: prompt_date(&mut stdout),
-                                        created: OffsetDateTime::now_local()?,
-                                    });
-                                }
-                            }
-                        }
-                    } else if ke.code == config.keybindings.edit_todo
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Todo(_))
+
+                            (true, (archive_time, archived))
+                        },
+                        |_t, _d, v| v,
+                        |_g, _d, v| v,
+                        0,
+                    );
+                    status.dirty |= archived;
+                }
+
+                let event = read()?;
+                if let Some(debug) = &mut status.debug {
+                    debug.last_event = Some(event.clone());
+                }
+
+                let action = match event {
+                    Event::Key(ke) => {
+                        let on = cursor_context(&config, &cursor, &settings);
+                        match bindings.press(&mut status.pending, KeyPress::from(ke), on) {
+                            ChordState::Complete | ChordState::NoMatch => {}
+                            ChordState::Pending => {
+                                draw_vis(
+                                    &mut stdout,
+                                    &mut renderer,
+                                    &config,
+                                    &cursor,
+                                    &mut status,
+                                    &settings,
+                                    &theme,
+                                )
+                                .ok();
+                                continue;
                             }
                         }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                if let HierarchyItemEnumMut::Todo(t) =
-                                    h.find_item_mut(&mut config)?.item
-                                {
-                                    let todo_name = prompt(&mut stdout, "Todo: ", &t.name)?;
-                                    if !todo_name.is_empty() {
-                                        t.name = todo_name;
-                                    }
+                        let keys = std::mem::take(&mut status.pending);
 
-                                    if let Some(due) = t.due {
-                                        t.due = Some(
-                                            prompt_date_in_place(&mut stdout, due).unwrap_or(due),
-                                        );
-                                    } else {
-                                        t.due = prompt_date(&mut stdout);
+                        bindings.resolve(&keys, on)
+                    }
+                    Event::Mouse(me) => {
+                        match (me.kind, &mut cursor) {
+                            (MouseEventKind::Down(MouseButton::Left), Cursor::Hierarchy(h)) => {
+                                h.indexes = vec![0];
+                                for _ in 1..me.row {
+                                    // Below the last item
+                                    if h.cursor_down(&config).is_err() {
+                                        break;
                                     }
                                 }
+                                Some(Action::Activate)
                             }
+                            _ => None,
                         }
-                    } else if ke.code == config.keybindings.add_group
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                if let HierarchyItemEnumMut::Group(g) =
-                                    h.find_item_mut(&mut config)?.item
-                                {
-                                    let group_name = prompt(&mut stdout, "Group: ", "")?;
-                                    g.subgroups.push(Group {
-                                        name: group_name,
-                                        hidden: false,
-                                        open: true,
-                                        todos: vec![],
-                                        completed: vec![],
-                                        todo_archive: vec![],
-                                        subgroups: vec![],
-                                        subgroup_archive: vec![],
-                                    });
-                                }
-                            }
-                        }
-                    } else if ke.code == config.keybindings.edit_group
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
+                    }
+                    // The next draw sees the new size and starts from a clear screen
+                    Event::Resize(..) => None,
+                    _ => None,
+                };
+
+                // The palette queues the action it picked along with any argument typed after it
+                let mut queue: VecDeque<(Action, Vec<String>)> =
+                    action.map(|a| (a, vec![])).into_iter().collect();
+
+                while let Some((action, answers)) = queue.pop_front() {
+                    let before = TodoSnapshot::take(&config);
+                    let result = dispatch(
+                        action,
+                        &mut config,
+                        &mut cursor,
+                        &mut PrefilledInput {
+                            answers: answers.into(),
+                            fallback: &mut TerminalInput(&mut stdout),
+                        },
+                        &settings,
+                    );
+                    // Prompts are drawn over the top row
+                    renderer.invalidate_row(0);
+                    let outcome = match result {
+                        Ok(outcome) => outcome,
+                        Err(e) => {
+                            // Mostly typos in palette arguments, say so and carry on
+                            status.error(e.to_string());
+                            continue;
                         }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                if let HierarchyItemEnumMut::Group(g) =
-                                    h.find_item_mut(&mut config)?.item
-                                {
-                                    let group_name =
-                                        prompt(&mut stdout, "Group: ", &format!("{} ", &g.name))?;
-                                    if !group_name.is_empty() {
-                                        g.name = group_name;
-                                    }
+                    };
+                    hooks.after_action(&before, &config);
+
+                    match outcome {
+                        Outcome::Done => {}
+                        Outcome::Changed => status.dirty = true,
+                        Outcome::Save => {
+                            match save(
+                                &config,
+                                &cursor,
+                                config_path,
+                                &mut saved,
+                                &mut hooks,
+                                &settings,
+                            ) {
+                                Ok(()) => {
+                                    status.dirty = false;
+                                    status.info(format!("Saved {}", config_path.display()))
                                 }
+                                Err(e) => status.error(format!("Couldn't save: {}", e)),
                             }
                         }
-                    } else if ke.code == config.keybindings.add_top_group {
-                        let group_name = prompt(&mut stdout, "Group: ", "")?;
-                        config.groups.push(Group {
-                            name: group_name,
-                            hidden: false,
-                            open: true,
-                            todos: vec![],
-                            completed: vec![],
-                            todo_archive: vec![],
-                            subgroups: vec![],
-                            subgroup_archive: vec![],
-                        });
-                    } else if ke.code == config.keybindings.move_group_down
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                let group = h.find_group_mut(&mut config)?;
-                                if h.last()? + 1 < group.subgroups.len() {
-                                    group.subgroups.swap(h.last()?, h.last()? + 1);
-                                    *h.last_mut()? += 1;
+                        Outcome::Quit => {
+                            // Staying open keeps the changes around to try again
+                            match save(
+                                &config,
+                                &cursor,
+                                config_path,
+                                &mut saved,
+                                &mut hooks,
+                                &settings,
+                            ) {
+                                Ok(()) => break 'main,
+                                Err(e) => {
+                                    status.error(format!("Couldn't save, not quitting: {}", e))
                                 }
                             }
                         }
-                    } else if ke.code == config.keybindings.move_group_up
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Group(_))
-                            }
+                        Outcome::QuitWithoutSaving => {
+                            // Only the cursor position, not worth staying open for
+                            ViewState::capture(&config, &cursor, &settings)
+                                .write_state(state_path)
+                                .ok();
+                            break 'main;
                         }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                let group = h.find_group_mut(&mut config)?;
-                                if h.last()? > 0 {
-                                    group.subgroups.swap(h.last()?, h.last()? - 1);
-                                    *h.last_mut()? -= 1;
-                                }
-                            }
+                        Outcome::ShowHelp => {
+                            // print help
+                            execute!(stdout, MoveTo(0, 0), Clear(ClearType::All), Print("Help:"))?;
+
+                            while !(matches!(read()?, Event::Key(_))) {}
+                            renderer.invalidate();
                         }
-                    } else if ke.code == config.keybindings.move_todo_down
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Todo(_))
+                        Outcome::RunCustom(i) => {
+                            let command = &settings.custom_commands[i];
+                            if let Some(running) = &custom {
+                                let name = &settings.custom_commands[running.index].name;
+                                status.error(format!("{} is still running", name));
+                                continue;
                             }
-                        }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                let group = h.find_group_mut(&mut config)?;
-                                if h.last()? + 1 < group.subgroups.len() + group.todos.len()
-                                    && h.last()? >= group.subgroups.len()
-                                {
-                                    group.todos.swap(
-                                        h.last()? - group.subgroups.len(),
-                                        h.last()? + 1 - group.subgroups.len(),
-                                    );
-                                    *h.last_mut()? += 1;
+                            match RunningCommand::start(
+                                i,
+                                command,
+                                &config,
+                                &cursor,
+                                config_path,
+                                &settings,
+                            ) {
+                                Ok(running) => {
+                                    status.running = Some(command.name.clone());
+                                    custom = Some(running);
                                 }
+                                Err(e) => status.error(format!("{} failed: {}", command.name, e)),
                             }
                         }
-                    } else if ke.code == config.keybindings.move_todo_up
-                        && match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                matches!(h.find_item(&config)?.item, HierarchyItemEnum::Todo(_))
-                            }
+                        Outcome::ToggleDebug => {
+                            status.debug = match status.debug {
+                                Some(_) => None,
+                                None => Some(DebugInfo::new(config_path)),
+                            };
                         }
-                    {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                let group = h.find_group_mut(&mut config)?;
-                                if h.last()? > group.subgroups.len()
-                                    && h.last()? < group.subgroups.len() + group.todos.len()
-                                {
-                                    group.todos.swap(
-                                        h.last()? - group.subgroups.len(),
-                                        h.last()? - 1 - group.subgroups.len(),
-                                    );
-                                    *h.last_mut()? -= 1;
-                                }
+                        Outcome::ShowPalette => {
+                            let picked = palette::run(&mut stdout, &bindings)?;
+                            renderer.invalidate();
+                            if let Some(picked) = picked {
+                                queue.push_back(picked);
                             }
                         }
-                    } else if ke.code == config.keybindings.clean
-                        && ke.modifiers.contains(KeyModifiers::ALT)
-                    {
-                        //cleanup
-                        config.archive_groups = vec![];
-                        for group in config.groups.iter_mut() {
-                            group.traverse_mut(
-                                (),
-                                |g, _d, v| {
-                                    g.todo_archive = vec![];
-                                    g.subgroup_archive = vec![];
-
-                                    (true, v)
-                                },
-                                |_t, _d, v| v,
-                                |_g, _d, v| v,
-                                0,
-                            );
-                        }
-                    } else if ke.code == config.keybindings.help {
-                        // print help
-                        execute!(stdout, MoveTo(0, 0), Clear(ClearType::All), Print("Help:"))?;
+                    }
+                }
 
-                        while !(matches!(read()?, Event::Key(_))) {}
+                if config.groups.is_empty() {
+                    match create_top_group(&mut config, &mut TerminalInput(&mut stdout)) {
+                        Ok(()) => status.dirty = true,
+                        Err(e) => status.error(format!("Couldn't add a group: {}", e)),
                     }
+                    renderer.invalidate_row(0);
                 }
-                Event::Mouse(me) => {
-                    if let MouseEventKind::Down(MouseButton::Left) = me.kind {
-                        match cursor {
-                            Cursor::Hierarchy(ref mut h) => {
-                                h.indexes = vec![0];
-                                for _ in 1..me.row {
-                                    h.cursor_down(&config)?;
-                                }
-                                activate_item(&mut cursor, &mut config)?;
-                            }
-                        }
+
+                // An action that left the cursor on nothing shouldn't keep every draw failing
+                if let Cursor::Hierarchy(h) = &mut cursor {
+                    if !config.groups.is_empty() && h.find_item(&config).is_err() {
+                        h.reset(&config);
+                        status.error("The cursor was lost, moved it back to the top");
                     }
                 }
-                _ => {}
-            }
 
-            if config.groups.is_empty() {
-                create_top_group(&mut config, &mut stdout)?;
+                draw_vis(
+                    &mut stdout,
+                    &mut renderer,
+                    &config,
+                    &cursor,
+                    &mut status,
+                    &settings,
+                    &theme,
+                )
+                .ok();
+            } else if status.expire() || minute() != drawn_minute {
+                // Nothing happened, but the message ran out or relative times and overdue todos
+                // have moved on
+                drawn_minute = minute();
+                draw_vis(
+                    &mut stdout,
+                    &mut renderer,
+                    &config,
+                    &cursor,
+                    &mut status,
+                    &settings,
+                    &theme,
+                )
+                .ok();
             }
-
-            draw_vis(&mut stdout, &config, &cursor).ok();
         }
-    }
 
-    execute!(
-        stdout,
-        Show,
-        DisableMouseCapture,
-        Clear(ClearType::All),
-        LeaveAlternateScreen
-    )?;
+        Ok(())
+    }));
 
-    disable_raw_mode()?;
+    drop(guard);
+    hooks.finish();
 
-    Ok(())
+    let unsaved = config.to_ron().map_or(true, |ron| ron != saved);
+    let dump = || {
+        if unsaved {
+            match recovery::dump_unsaved(config_path, &config) {
+                Ok(path) => eprintln!("Unsaved changes were written to {}", path.display()),
+                Err(e) => eprintln!("Couldn't write unsaved changes anywhere: {}", e),
+            }
+        }
+    };
+    match result {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => {
+            dump();
+            Err(e)
+        }
+        Err(panic) => {
+            dump();
+            panic::resume_unwind(panic)
+        }
+    }
 }
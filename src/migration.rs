@@ -0,0 +1,649 @@
+use std::path::{Path, PathBuf};
+
+use std::collections::BTreeSet;
+
+use crate::{
+    keybindings::{Binding, KeyPress},
+    settings::{Settings, SETTINGS_VERSION},
+    todo_config::{new_id, ConfigError},
+    view_state::ViewState,
+};
+use crossterm::event::{KeyCode, KeyModifiers};
+use time::{Duration, OffsetDateTime};
+
+pub const CONFIG_VERSION: u32 = 3; // Version written by this build
+
+#[derive(serde::Deserialize)]
+struct VersionProbe {
+    #[serde(default)]
+    version: u32, // Data files from before versioning have no version field
+}
+
+#[derive(serde::Deserialize)]
+struct SettingsVersionProbe {
+    #[serde(default)]
+    version: u32, // 0 when left out, by hand-written settings and ones from before versioning
+}
+
+fn one_day() -> Duration {
+    Duration::days(1)
+}
+
+// Keybindings up to settings version 1, one bare key per action.
+// Alt + quit and Alt + clean were hard-coded on top of these.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct KeybindingsV1 {
+    add_todo: KeyCode,
+    add_group: KeyCode,
+    add_top_group: KeyCode,
+    toggle_group: KeyCode,
+    toggle_todo: KeyCode,
+    archive_todo: KeyCode,
+    hide_group: KeyCode,
+    edit_todo: KeyCode,
+    edit_group: KeyCode,
+    move_todo_up: KeyCode,
+    move_todo_down: KeyCode,
+    move_group_up: KeyCode,
+    move_group_down: KeyCode,
+    cursor_up: KeyCode,
+    cursor_down: KeyCode,
+    group_up: KeyCode,
+    group_down: KeyCode,
+    hierarchy_up: KeyCode,
+    hierarchy_down: KeyCode,
+    quit: KeyCode,
+    save: KeyCode,
+    clean: KeyCode,
+    help: KeyCode,
+}
+
+impl Default for KeybindingsV1 {
+    fn default() -> Self {
+        Self {
+            add_todo: KeyCode::Char('a'),
+            add_group: KeyCode::Char('g'),
+            add_top_group: KeyCode::Char('n'),
+            toggle_group: KeyCode::Char(' '),
+            toggle_todo: KeyCode::Char(' '),
+            archive_todo: KeyCode::Char('d'),
+            hide_group: KeyCode::Char('x'),
+            edit_todo: KeyCode::Char('e'),
+            edit_group: KeyCode::Char('e'),
+            move_todo_up: KeyCode::Char('i'),
+            move_todo_down: KeyCode::Char('k'),
+            move_group_up: KeyCode::Char('i'),
+            move_group_down: KeyCode::Char('k'),
+            cursor_up: KeyCode::Up,
+            cursor_down: KeyCode::Down,
+            group_up: KeyCode::PageUp,
+            group_down: KeyCode::PageDown,
+            hierarchy_up: KeyCode::Char('['),
+            hierarchy_down: KeyCode::Char(']'),
+            quit: KeyCode::Char('q'),
+            save: KeyCode::Char('s'),
+            clean: KeyCode::Char('o'),
+            help: KeyCode::Char('h'),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SettingsV1 {
+    #[serde(default = "one_day")]
+    archive_time: Duration,
+    #[serde(default)]
+    keybindings: KeybindingsV1,
+}
+
+// Settings version 2, the first with key sequences. Later fields are left out and start at
+// their defaults.
+#[derive(serde::Serialize)]
+struct SettingsV2 {
+    version: u32,
+    archive_time: Duration,
+    keybindings: KeybindingsV2,
+}
+
+#[derive(serde::Serialize)]
+struct KeybindingsV2 {
+    add_todo: Binding,
+    add_group: Binding,
+    add_top_group: Binding,
+    toggle_group: Binding,
+    toggle_todo: Binding,
+    archive_todo: Binding,
+    hide_group: Binding,
+    edit_todo: Binding,
+    edit_group: Binding,
+    move_todo_up: Binding,
+    move_todo_down: Binding,
+    move_group_up: Binding,
+    move_group_down: Binding,
+    cursor_up: Binding,
+    cursor_down: Binding,
+    group_up: Binding,
+    group_down: Binding,
+    hierarchy_up: Binding,
+    hierarchy_down: Binding,
+    quit: Binding,
+    quit_without_saving: Binding,
+    save: Binding,
+    clean: Binding,
+    help: Binding,
+}
+
+// Todos and groups up to version 2, before ids and with `open` stored in the data.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TodoV2 {
+    name: String,
+    done_time: Option<OffsetDateTime>,
+    due: Option<OffsetDateTime>,
+    created: OffsetDateTime,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GroupV2 {
+    hidden: bool,
+    name: String,
+    open: bool,
+    #[serde(default)]
+    todos: Vec<TodoV2>,
+    #[serde(default)]
+    completed: Vec<TodoV2>,
+    #[serde(default)]
+    todo_archive: Vec<TodoV2>,
+    #[serde(default)]
+    subgroups: Vec<GroupV2>,
+    #[serde(default)]
+    subgroup_archive: Vec<GroupV2>,
+}
+
+// Layout of version 0 and 1 files. Version 0 is the same layout without the version field.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ConfigV1 {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    groups: Vec<GroupV2>,
+    #[serde(default)]
+    archive_groups: Vec<GroupV2>,
+    archive_time: Duration,
+    keybindings: KeybindingsV1,
+}
+
+// Version 2 moved archive_time and keybindings out into settings.ron.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DataV2 {
+    version: u32,
+    groups: Vec<GroupV2>,
+    archive_groups: Vec<GroupV2>,
+}
+
+// Version 3 gave every todo and group an id and moved `open` into <file>.state.ron. Fields added
+// since all have defaults, so version 3 files load as they are.
+#[derive(serde::Serialize)]
+struct TodoV3 {
+    id: u64,
+    name: String,
+    done_time: Option<OffsetDateTime>,
+    due: Option<OffsetDateTime>,
+    created: OffsetDateTime,
+}
+
+#[derive(serde::Serialize)]
+struct GroupV3 {
+    id: u64,
+    hidden: bool,
+    name: String,
+    todos: Vec<TodoV3>,
+    completed: Vec<TodoV3>,
+    todo_archive: Vec<TodoV3>,
+    subgroups: Vec<GroupV3>,
+    subgroup_archive: Vec<GroupV3>,
+}
+
+#[derive(serde::Serialize)]
+struct DataV3 {
+    version: u32,
+    groups: Vec<GroupV3>,
+    archive_groups: Vec<GroupV3>,
+}
+
+// Files besides the one being migrated that a step may need to write to
+struct MigrationContext<'a> {
+    settings_path: &'a Path,
+    state_path: &'a Path,
+}
+
+type Migration = fn(&str, &MigrationContext) -> Result<String, ConfigError>;
+
+// MIGRATIONS[n] upgrades a version n data file to version n + 1.
+// 2 moved archive_time and keybindings out into settings.ron,
+// 3 gave every todo and group an id and moved `open` into <file>.state.ron.
+// Fields added with a default, like due dates on groups, don't need a step.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3];
+
+type SettingsMigration = fn(&str) -> Result<String, ConfigError>;
+
+// SETTINGS_MIGRATIONS[n] upgrades a version n + 1 settings file to version n + 2.
+// 2 replaced bare key codes with key sequence strings.
+const SETTINGS_MIGRATIONS: [SettingsMigration; SETTINGS_VERSION as usize - 1] = [settings_v1_to_v2];
+
+// Upgrades a data file to CONFIG_VERSION. Returns None if it is already current.
+pub fn upgrade(
+    source: &str,
+    config_path: &Path,
+    settings_path: &Path,
+    state_path: &Path,
+) -> Result<Option<String>, ConfigError> {
+    let version = ron::from_str::<VersionProbe>(source)?.version;
+    if !prepare(config_path, version, CONFIG_VERSION)? {
+        return Ok(None);
+    }
+
+    let context = MigrationContext {
+        settings_path,
+        state_path,
+    };
+    let mut source = source.to_string();
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        source = migration(&source, &context).map_err(in_step(step as u32, version))?;
+    }
+
+    Ok(Some(source))
+}
+
+// Upgrades a settings file to SETTINGS_VERSION. Returns None if it is already current.
+pub fn upgrade_settings(source: &str, settings_path: &Path) -> Result<Option<String>, ConfigError> {
+    let version = match ron::from_str::<SettingsVersionProbe>(source)?.version {
+        // Without a version it is current if it reads as current settings, older otherwise
+        0 if ron::from_str::<Settings>(source).is_ok() => SETTINGS_VERSION,
+        0 => 1,
+        version => version,
+    };
+    if !prepare(settings_path, version, SETTINGS_VERSION)? {
+        return Ok(None);
+    }
+
+    let mut source = source.to_string();
+    for (step, migration) in SETTINGS_MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(version as usize - 1)
+    {
+        source = migration(&source).map_err(in_step(step as u32 + 1, version))?;
+    }
+
+    Ok(Some(source))
+}
+
+// Only the first step reads the file itself, the ones after read what the step before wrote. Their
+// parse errors point into text that was never on disk, so they say which version failed instead.
+fn in_step(reads: u32, file_version: u32) -> impl Fn(ConfigError) -> ConfigError {
+    move |err| match err {
+        ConfigError::Parse(err) if reads > file_version => ConfigError::Migration {
+            version: reads,
+            err,
+        },
+        err => err,
+    }
+}
+
+// Refuses files newer than `current` and backs up older ones. Returns whether to migrate.
+fn prepare(path: &Path, version: u32, current: u32) -> Result<bool, ConfigError> {
+    if version > current {
+        return Err(ConfigError::TooNew {
+            found: version,
+            supported: current,
+        });
+    }
+    if version == current {
+        return Ok(false);
+    }
+
+    std::fs::copy(path, backup_path(path, &format!("v{}", version)))?;
+    Ok(true)
+}
+
+// <file>.<tag>.bak next to the config file
+pub fn backup_path(config_path: &Path, tag: &str) -> PathBuf {
+    let mut name = config_path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(format!(".{}.bak", tag));
+    config_path.with_file_name(name)
+}
+
+fn to_string(value: &impl serde::Serialize) -> Result<String, ConfigError> {
+    Ok(ron::ser::to_string_pretty(
+        value,
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+fn v0_to_v1(source: &str, _context: &MigrationContext) -> Result<String, ConfigError> {
+    let mut config: ConfigV1 = ron::from_str(source)?;
+    config.version = 1;
+    to_string(&config)
+}
+
+fn v1_to_v2(source: &str, context: &MigrationContext) -> Result<String, ConfigError> {
+    let config: ConfigV1 = ron::from_str(source)?;
+
+    // An existing settings.ron wins, the backup still has the old preferences
+    if !context.settings_path.exists() {
+        std::fs::write(
+            context.settings_path,
+            to_string(&SettingsV2 {
+                version: 2,
+                archive_time: config.archive_time,
+                keybindings: config.keybindings.into(),
+            })?,
+        )?;
+    }
+
+    to_string(&DataV2 {
+        version: 2,
+        groups: config.groups,
+        archive_groups: config.archive_groups,
+    })
+}
+
+fn v2_to_v3(source: &str, context: &MigrationContext) -> Result<String, ConfigError> {
+    let data: DataV2 = ron::from_str(source)?;
+
+    // Carry the open groups over so the tree looks the same after upgrading
+    let mut state = ViewState::read_state(context.state_path).unwrap_or_default();
+    let mut upgrade = |groups: Vec<GroupV2>| {
+        groups
+            .into_iter()
+            .map(|g| g.upgrade(&mut state.open_groups))
+            .collect()
+    };
+    let data = DataV3 {
+        version: 3,
+        groups: upgrade(data.groups),
+        archive_groups: upgrade(data.archive_groups),
+    };
+    state.write_state(context.state_path)?;
+
+    to_string(&data)
+}
+
+fn settings_v1_to_v2(source: &str) -> Result<String, ConfigError> {
+    let settings: SettingsV1 = ron::from_str(source)?;
+    to_string(&SettingsV2 {
+        version: 2,
+        archive_time: settings.archive_time,
+        keybindings: settings.keybindings.into(),
+    })
+}
+
+impl From<KeybindingsV1> for KeybindingsV2 {
+    fn from(old: KeybindingsV1) -> Self {
+        let key = |code, modifiers| Binding::from(KeyPress::new(code, modifiers));
+        let none = KeyModifiers::NONE;
+
+        Self {
+            add_todo: key(old.add_todo, none),
+            add_group: key(old.add_group, none),
+            add_top_group: key(old.add_top_group, none),
+            toggle_group: key(old.toggle_group, none),
+            toggle_todo: key(old.toggle_todo, none),
+            archive_todo: key(old.archive_todo, none),
+            hide_group: key(old.hide_group, none),
+            edit_todo: key(old.edit_todo, none),
+            edit_group: key(old.edit_group, none),
+            move_todo_up: key(old.move_todo_up, none),
+            move_todo_down: key(old.move_todo_down, none),
+            move_group_up: key(old.move_group_up, none),
+            move_group_down: key(old.move_group_down, none),
+            cursor_up: key(old.cursor_up, none),
+            cursor_down: key(old.cursor_down, none),
+            group_up: key(old.group_up, none),
+            group_down: key(old.group_down, none),
+            hierarchy_up: key(old.hierarchy_up, none),
+            hierarchy_down: key(old.hierarchy_down, none),
+            quit_without_saving: key(old.quit, KeyModifiers::ALT),
+            quit: key(old.quit, none),
+            save: key(old.save, none),
+            clean: key(old.clean, KeyModifiers::ALT),
+            help: key(old.help, none),
+        }
+    }
+}
+
+impl From<TodoV2> for TodoV3 {
+    fn from(todo: TodoV2) -> Self {
+        Self {
+            id: new_id(),
+            name: todo.name,
+            done_time: todo.done_time,
+            due: todo.due,
+            created: todo.created,
+        }
+    }
+}
+
+impl GroupV2 {
+    // Gives the group and everything in it ids, adding the open ones to `open`
+    fn upgrade(self, open: &mut BTreeSet<u64>) -> GroupV3 {
+        let id = new_id();
+        if self.open {
+            open.insert(id);
+        }
+        let todos = |todos: Vec<TodoV2>| todos.into_iter().map(TodoV3::from).collect();
+        let mut groups = |groups: Vec<GroupV2>| {
+            groups
+                .into_iter()
+                .map(|g| g.upgrade(open))
+                .collect::<Vec<_>>()
+        };
+
+        GroupV3 {
+            id,
+            hidden: self.hidden,
+            name: self.name,
+            todos: todos(self.todos),
+            completed: todos(self.completed),
+            todo_archive: todos(self.todo_archive),
+            subgroups: groups(self.subgroups),
+            subgroup_archive: groups(self.subgroup_archive),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        navigation::PositionHierarchy,
+        todo_config::{Priority, TodoConfig},
+    };
+
+    use super::*;
+
+    // A folder of its own in the temp dir, for the files migrating writes next to the data
+    fn folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("dmc-todo-{}-{}", name, new_id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn group(name: &str, open: bool, todos: &[&str], subgroups: Vec<GroupV2>) -> GroupV2 {
+        GroupV2 {
+            hidden: false,
+            name: name.to_string(),
+            open,
+            todos: todos
+                .iter()
+                .map(|name| TodoV2 {
+                    name: name.to_string(),
+                    done_time: None,
+                    due: None,
+                    created: OffsetDateTime::UNIX_EPOCH,
+                })
+                .collect(),
+            completed: vec![],
+            todo_archive: vec![],
+            subgroups,
+            subgroup_archive: vec![],
+        }
+    }
+
+    // A file from before versioning, with no version field and the settings in it
+    fn v0_source() -> String {
+        let config = ConfigV1 {
+            version: 0,
+            groups: vec![group(
+                "School",
+                true,
+                &[],
+                vec![group("AP CSP", false, &["Computering"], vec![])],
+            )],
+            archive_groups: vec![],
+            archive_time: Duration::hours(2),
+            keybindings: KeybindingsV1 {
+                save: KeyCode::Char('w'),
+                ..Default::default()
+            },
+        };
+        to_string(&config)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.contains("version"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn v0_to_latest() {
+        let folder = folder("v0");
+        let config_path = folder.join("todo.ron");
+        let settings_path = folder.join("settings.ron");
+        let state_path = ViewState::path_for(&config_path);
+        let source = v0_source();
+        std::fs::write(&config_path, &source).unwrap();
+
+        let upgraded = upgrade(&source, &config_path, &settings_path, &state_path)
+            .unwrap()
+            .unwrap();
+        let config: TodoConfig = ron::from_str(&upgraded).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.groups[0].name, "School");
+        assert_eq!(config.groups[0].subgroups[0].name, "AP CSP");
+        let todo = &config.groups[0].subgroups[0].todos[0];
+        assert_eq!(todo.name, "Computering");
+        assert_eq!(todo.priority, Priority::Normal);
+        assert!(PositionHierarchy::find_id(&config, todo.id).is_some());
+
+        // The settings moved out with what was customised, and the open groups into the state
+        let settings = Settings::read_settings(&settings_path).unwrap();
+        assert_eq!(settings.archive_time, Duration::hours(2));
+        assert_eq!(
+            settings.keybindings.save,
+            Binding::from(KeyPress::new(KeyCode::Char('w'), KeyModifiers::NONE))
+        );
+        let state = ViewState::read_state(&state_path).unwrap();
+        assert!(state.open_groups.contains(&config.groups[0].id));
+        assert!(!state
+            .open_groups
+            .contains(&config.groups[0].subgroups[0].id));
+
+        assert!(backup_path(&config_path, "v0").exists());
+        std::fs::remove_dir_all(folder).ok();
+    }
+
+    #[test]
+    fn settings_v1_to_latest() {
+        let folder = folder("settings");
+        let settings_path = folder.join("settings.ron");
+        let source = "(version: 1, archive_time: (7200, 0), keybindings: (save: Char('w')))";
+        std::fs::write(&settings_path, source).unwrap();
+
+        let upgraded = upgrade_settings(source, &settings_path).unwrap().unwrap();
+        let settings: Settings = ron::from_str(&upgraded).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.archive_time, Duration::hours(2));
+        assert_eq!(
+            settings.keybindings.save,
+            Binding::from(KeyPress::new(KeyCode::Char('w'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            settings.keybindings.quit_without_saving,
+            Binding::from(KeyPress::new(KeyCode::Char('q'), KeyModifiers::ALT))
+        );
+        assert!(backup_path(&settings_path, "v1").exists());
+        std::fs::remove_dir_all(folder).ok();
+    }
+
+    #[test]
+    fn later_steps_say_which_version_failed() {
+        let parse = || ConfigError::Parse(ron::from_str::<u32>("nope").unwrap_err());
+        // The first step's errors are in the file and keep their position
+        assert!(matches!(in_step(1, 1)(parse()), ConfigError::Parse(_)));
+        assert!(matches!(
+            in_step(2, 1)(parse()),
+            ConfigError::Migration { version: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn unversioned_settings() {
+        let folder = folder("unversioned");
+        let settings_path = folder.join("settings.ron");
+
+        let old = "(keybindings: (save: Char('w')))";
+        std::fs::write(&settings_path, old).unwrap();
+        let upgraded = upgrade_settings(old, &settings_path).unwrap().unwrap();
+        let settings: Settings = ron::from_str(&upgraded).unwrap();
+        assert_eq!(
+            settings.keybindings.save,
+            Binding::from(KeyPress::new(KeyCode::Char('w'), KeyModifiers::NONE))
+        );
+
+        let current = r#"(keybindings: (save: "C-s"))"#;
+        assert!(upgrade_settings(current, &settings_path).unwrap().is_none());
+        std::fs::remove_dir_all(folder).ok();
+    }
+
+    #[test]
+    fn current_is_left_alone() {
+        let folder = folder("current");
+        let config_path = folder.join("todo.ron");
+        let source = TodoConfig {
+            version: CONFIG_VERSION,
+            groups: vec![],
+            archive_groups: vec![],
+        }
+        .to_ron()
+        .unwrap();
+
+        let upgraded = upgrade(
+            &source,
+            &config_path,
+            &folder.join("settings.ron"),
+            &folder.join("todo.state.ron"),
+        )
+        .unwrap();
+        assert!(upgraded.is_none());
+        std::fs::remove_dir_all(folder).ok();
+    }
+
+    #[test]
+    fn newer_is_refused() {
+        let folder = folder("newer");
+        let source = format!("(version: {}, groups: [])", CONFIG_VERSION + 1);
+
+        let upgraded = upgrade(
+            &source,
+            &folder.join("todo.ron"),
+            &folder.join("settings.ron"),
+            &folder.join("todo.state.ron"),
+        );
+        assert!(matches!(upgraded, Err(ConfigError::TooNew { .. })));
+        std::fs::remove_dir_all(folder).ok();
+    }
+}
@@ -1,9 +1,18 @@
 use thiserror::Error;
 
-use crate::todo_config::{Group, Todo, TodoConfig};
-
+use crate::{
+    agenda_view::AgendaView,
+    board_view::BoardView,
+    calendar_view::CalendarView,
+    list_view::ListView,
+    settings::Settings,
+    todo_config::{Group, Todo, TodoConfig},
+};
+
+#[derive(Clone)]
 pub struct PositionHierarchy {
     pub indexes: Vec<usize>, /* indexes except last are group index only (cant have todo in todo). last one is group > todo > todoDone (as drawn on screen) */
+    pub root: Vec<usize>, // Path to the group zoomed into, drawn as the only top level one. indexes start from it
 }
 
 impl PositionHierarchy {
@@ -34,6 +43,15 @@ pub enum HierarchyItemEnumMut<'a> {
     Group(&'a mut Group),
 }
 
+impl HierarchyItemEnum<'_> {
+    pub fn id(&self) -> u64 {
+        match self {
+            HierarchyItemEnum::Todo(t) => t.id,
+            HierarchyItemEnum::Group(g) => g.id,
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum MoveError {
     #[error("No index items. This should not happen.")]
@@ -46,12 +64,215 @@ pub enum MoveError {
 
 impl PositionHierarchy {
     pub fn new() -> Self {
-        Self { indexes: vec![0] }
+        Self {
+            indexes: vec![0],
+            root: vec![],
+        }
+    }
+
+    // Position of the group or todo with the given id
+    pub fn find_id(context: &TodoConfig, id: u64) -> Option<Self> {
+        context.groups.iter().enumerate().find_map(|(i, g)| {
+            let mut indexes = Self::path_to(g, id)?;
+            indexes.insert(0, i);
+            Some(Self {
+                indexes,
+                root: vec![],
+            })
+        })
+    }
+
+    // Position of the group at the end of a "Top/Sub/Subsub" path of group names
+    pub fn find_group_path(context: &TodoConfig, path: &str) -> Option<Self> {
+        let mut names = path.split('/').map(str::trim).filter(|n| !n.is_empty());
+
+        let first = names.next()?;
+        let top = context.groups.iter().position(|g| g.name.trim() == first)?;
+        let mut indexes = vec![top];
+        let mut group = &context.groups[top];
+
+        for name in names {
+            let i = group.subgroups.iter().position(|g| g.name.trim() == name)?;
+            indexes.push(i);
+            group = &group.subgroups[i];
+        }
+
+        Some(Self {
+            indexes,
+            root: vec![],
+        })
+    }
+
+    // Opens every group above the position so it is visible
+    pub fn reveal(&self, context: &mut TodoConfig) {
+        let indexes = self.absolute();
+        let mut group: Option<&mut Group> = None;
+        for &index in &indexes[..indexes.len() - 1] {
+            let next = match group {
+                None => context.groups.get_mut(index),
+                Some(g) => g.subgroups.get_mut(index),
+            };
+            if let Some(g) = next {
+                g.open = true;
+                group = Some(g);
+            } else {
+                break;
+            }
+        }
+    }
+
+    // Names of the groups the position is inside, top first
+    pub fn group_names<'a>(&self, context: &'a TodoConfig) -> Vec<&'a str> {
+        let mut names = vec![];
+        let indexes = self.absolute();
+        let mut groups = &context.groups;
+        for &index in &indexes[..indexes.len().saturating_sub(1)] {
+            let Some(group) = groups.get(index) else {
+                break;
+            };
+            names.push(group.name.as_str());
+            groups = &group.subgroups;
+        }
+        names
+    }
+
+    // The position from the top of the tree, ignoring the zoom
+    pub fn absolute(&self) -> Vec<usize> {
+        if self.root.is_empty() {
+            return self.indexes.clone();
+        }
+        self.root
+            .iter()
+            .chain(&self.indexes[1..])
+            .copied()
+            .collect()
+    }
+
+    // The top level groups, or just the zoomed in one
+    pub fn top<'a>(&self, context: &'a TodoConfig) -> &'a [Group] {
+        let Some((&first, rest)) = self.root.split_first() else {
+            return &context.groups;
+        };
+        let mut group = match context.groups.get(first) {
+            Some(g) => g,
+            None => return &[],
+        };
+        for &index in rest {
+            group = match group.subgroups.get(index) {
+                Some(g) => g,
+                None => return &[],
+            };
+        }
+        std::slice::from_ref(group)
+    }
+
+    pub fn top_mut<'a>(&self, context: &'a mut TodoConfig) -> &'a mut [Group] {
+        let Some((&first, rest)) = self.root.split_first() else {
+            return &mut context.groups;
+        };
+        let mut group = match context.groups.get_mut(first) {
+            Some(g) => g,
+            None => return &mut [],
+        };
+        for &index in rest {
+            group = match group.subgroups.get_mut(index) {
+                Some(g) => g,
+                None => return &mut [],
+            };
+        }
+        std::slice::from_mut(group)
+    }
+
+    // Moves to the item with the id, staying zoomed in if it is still inside
+    pub fn refind(&mut self, context: &TodoConfig, id: u64) {
+        match Self::find_id(context, id) {
+            Some(found) => *self = found.zoomed(&self.root),
+            None => self.reset(context),
+        }
+    }
+
+    // The same item zoomed into root, or zoomed all the way out if it isn't in there
+    pub fn zoomed(self, root: &[usize]) -> Self {
+        if root.is_empty() || !self.indexes.starts_with(root) {
+            return self;
+        }
+        let mut indexes = vec![0];
+        indexes.extend(&self.indexes[root.len()..]);
+        Self {
+            indexes,
+            root: root.to_vec(),
+        }
+    }
+
+    // Back to the top, staying zoomed in while the group is there
+    pub fn reset(&mut self, context: &TodoConfig) {
+        if self.top(context).is_empty() {
+            self.root.clear();
+        }
+        self.indexes = vec![0];
+    }
+
+    // Zooms into the group under the position, or the one the todo there is in
+    pub fn zoom_in(&mut self, context: &mut TodoConfig) -> Result<(), MoveError> {
+        let absolute = self.absolute();
+        match self.find_item(context)?.item {
+            HierarchyItemEnum::Group(_) => {
+                self.root = absolute;
+                self.indexes = vec![0];
+            }
+            HierarchyItemEnum::Todo(_) => {
+                self.root = absolute[..absolute.len() - 1].to_vec();
+                self.indexes = vec![0, self.last()?];
+            }
+        }
+        for group in self.top_mut(context) {
+            group.open = true;
+        }
+        Ok(())
+    }
+
+    // Zooms out a level, keeping the cursor where it is
+    pub fn zoom_out(&mut self) {
+        let Some(index) = self.root.pop() else {
+            return;
+        };
+        let mut indexes = if self.root.is_empty() {
+            vec![index]
+        } else {
+            vec![0, index]
+        };
+        indexes.extend(&self.indexes[1..]);
+        self.indexes = indexes;
+    }
+
+    // Whether the id is anywhere below the group
+    pub fn find_id_in(group: &Group, id: u64) -> bool {
+        group.id != id && Self::path_to(group, id).is_some()
+    }
+
+    fn path_to(group: &Group, id: u64) -> Option<Vec<usize>> {
+        if group.id == id {
+            return Some(vec![]);
+        }
+
+        for (i, subgroup) in group.subgroups.iter().enumerate() {
+            if let Some(mut path) = Self::path_to(subgroup, id) {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+
+        group
+            .todos
+            .iter()
+            .chain(group.completed.iter())
+            .position(|t| t.id == id)
+            .map(|i| vec![group.subgroups.len() + i])
     }
 
     pub fn find_item<'a>(&self, context: &'a TodoConfig) -> Result<HierarchyItem<'a>, MoveError> {
-        let mut group: &Group = context
-            .groups
+        let mut group: &Group = self
+            .top(context)
             .get(self.indexes[0])
             .ok_or(MoveError::GroupNotFound)?;
 
@@ -121,8 +342,8 @@ impl PositionHierarchy {
         &self,
         context: &'a mut TodoConfig,
     ) -> Result<HierarchyItemMut<'a>, MoveError> {
-        let mut group: &mut Group = context
-            .groups
+        let mut group: &mut Group = self
+            .top_mut(context)
             .get_mut(self.indexes[0])
             .ok_or(MoveError::GroupNotFound)?;
 
@@ -190,8 +411,8 @@ impl PositionHierarchy {
 
     // Finds the group ignoring the final index
     pub fn find_group<'a>(&self, context: &'a TodoConfig) -> Result<&'a Group, MoveError> {
-        let mut group: &Group = context
-            .groups
+        let mut group: &Group = self
+            .top(context)
             .get(self.indexes[0])
             .ok_or(MoveError::GroupNotFound)?;
 
@@ -214,8 +435,8 @@ impl PositionHierarchy {
         &self,
         context: &'a mut TodoConfig,
     ) -> Result<&'a mut Group, MoveError> {
-        let mut group: &mut Group = context
-            .groups
+        let mut group: &mut Group = self
+            .top_mut(context)
             .get_mut(self.indexes[0])
             .ok_or(MoveError::GroupNotFound)?;
 
@@ -259,7 +480,7 @@ impl PositionHierarchy {
         } else if self.indexes.len() == 1 {
             // special handling for first top-level group (wrap around to final)
 
-            *self.last_mut()? = context.groups.len() - 1;
+            *self.last_mut()? = self.top(context).len() - 1;
 
             while let HierarchyItemEnum::Group(g) = self.find_item(context)?.item {
                 if g.open && !g.is_empty() {
@@ -291,10 +512,18 @@ impl PositionHierarchy {
 
         // let group_count = self.find_group(context)?.len();
 
-        while *self.indexes.last().ok_or(MoveError::NoIndex)? + 1 >= self.find_group(context)?.len()
+        let start = self.indexes.clone();
+        while self.indexes.len() > 1
+            && *self.indexes.last().ok_or(MoveError::NoIndex)? + 1
+                >= self.find_group(context)?.len()
         {
             self.hierarchy_up(context)?;
         }
+        // Already on the last thing, the zoomed in group has nothing after it
+        if self.indexes.len() == 1 && self.last()? + 1 >= self.top(context).len() {
+            self.indexes = start;
+            return Ok(());
+        }
         self.group_down(context)?;
 
         Ok(())
@@ -312,7 +541,7 @@ impl PositionHierarchy {
 
     pub fn group_down(&mut self, context: &TodoConfig) -> Result<(), MoveError> {
         if self.indexes.len() == 1 {
-            if *self.indexes.last().ok_or(MoveError::NoIndex)? < context.groups.len() - 1 {
+            if *self.indexes.last().ok_or(MoveError::NoIndex)? < self.top(context).len() - 1 {
                 *(self.indexes.last_mut().ok_or(MoveError::NoIndex)?) += 1;
             }
             return Ok(());
@@ -366,13 +595,13 @@ impl PositionHierarchy {
     pub fn vert_pos(&self, context: &TodoConfig) -> Result<usize, MoveError> {
         // Finds the vertical position of the cursor in the context. 0 is top level group.
         let mut total = 0;
+        let top = self.top(context);
 
         for i in 0..*self.indexes.first().ok_or(MoveError::NoIndex)? {
-            total += Self::group_size(context.groups.get(i).ok_or(MoveError::GroupNotFound)?);
+            total += Self::group_size(top.get(i).ok_or(MoveError::GroupNotFound)?);
         }
 
-        let mut current_group = context
-            .groups
+        let mut current_group = top
             .get(*self.indexes.first().ok_or(MoveError::NoIndex)?)
             .ok_or(MoveError::GroupNotFound)?;
 
@@ -430,5 +659,21 @@ impl Default for PositionHierarchy {
 
 pub enum Cursor {
     Hierarchy(PositionHierarchy),
-    // Flat(FlatHierarchy),
+    List(ListView),
+    Board(BoardView),
+    Calendar(CalendarView),
+    Agenda(AgendaView),
+}
+
+impl Cursor {
+    // Where the item under the cursor is in the tree, for views that aren't the tree
+    pub fn position(&self, context: &TodoConfig, settings: &Settings) -> Option<PositionHierarchy> {
+        match self {
+            Cursor::Hierarchy(h) => Some(h.clone()),
+            Cursor::List(list) => list.position(context, settings),
+            Cursor::Board(board) => board.position(context, settings),
+            Cursor::Calendar(calendar) => calendar.position(context),
+            Cursor::Agenda(agenda) => agenda.position(context),
+        }
+    }
 }
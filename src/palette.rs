@@ -0,0 +1,147 @@
+use std::io::{Stdout, Write};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{read, Event, KeyCode},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{Clear, ClearType},
+};
+
+use crate::{
+    action::Action,
+    keybindings::{Binding, Bindings},
+};
+
+const MAX_SHOWN: usize = 12;
+
+// `:` followed by an action name, fuzzy matched, and an optional argument that answers the
+// action's first prompt. `move School/AP CSP` runs move_to with "School/AP CSP".
+// Returns None if cancelled with Esc.
+pub fn run(stdout: &mut Stdout, bindings: &Bindings) -> Result<Option<(Action, Vec<String>)>> {
+    let mut out = String::new();
+    let mut selected = 0;
+
+    loop {
+        let (query, argument) = split_input(&out);
+        let matches = matches(query, bindings);
+        selected = selected.min(matches.len().min(MAX_SHOWN).saturating_sub(1));
+
+        draw(stdout, &out, &matches, selected)?;
+
+        if let Event::Key(ke) = read()? {
+            match ke.code {
+                KeyCode::Char(c) => {
+                    out.push(c);
+                    selected = 0;
+                }
+                KeyCode::Backspace => {
+                    out.pop();
+                    selected = 0;
+                }
+                KeyCode::Up | KeyCode::BackTab => selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Tab => selected += 1,
+                KeyCode::Esc => break,
+                KeyCode::Enter => {
+                    if let Some((action, _, _)) = matches.get(selected) {
+                        let arguments = argument.map(|a| vec![a.to_string()]).unwrap_or_default();
+                        queue!(stdout, Hide)?;
+                        return Ok(Some((*action, arguments)));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    queue!(stdout, Hide)?;
+    Ok(None)
+}
+
+// Action name and the rest of the line
+fn split_input(input: &str) -> (&str, Option<&str>) {
+    match input.trim_start().split_once(' ') {
+        Some((query, argument)) if !argument.trim().is_empty() => (query, Some(argument.trim())),
+        Some((query, _)) => (query, None),
+        None => (input.trim_start(), None),
+    }
+}
+
+// Bound and unbound actions matching `query`, best first
+fn matches<'a>(query: &str, bindings: &Bindings<'a>) -> Vec<(Action, &'a str, &'a Binding)> {
+    let mut scored: Vec<(usize, Action, &str, &Binding)> = bindings
+        .0
+        .iter()
+        .filter(|(action, _, _)| *action != Action::CommandPalette)
+        .filter_map(|&(action, name, binding)| {
+            Some((fuzzy_score(query, name)?, action, name, binding))
+        })
+        .collect();
+
+    scored.sort_by_key(|(score, _, _, _)| *score);
+    scored.into_iter().map(|(_, a, n, b)| (a, n, b)).collect()
+}
+
+// Lower is better. None if the characters of `query` don't all appear in `name` in order.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    if query.is_empty() {
+        return Some(0); // Keep the settings.ron order
+    }
+
+    let mut first = None;
+    let mut gaps = 0;
+    let mut last = 0;
+    let mut chars = name.char_indices();
+
+    for q in query.chars().map(|c| c.to_ascii_lowercase()) {
+        let q = if q == ' ' || q == '-' { '_' } else { q };
+        let (i, _) = chars.by_ref().find(|(_, c)| c.to_ascii_lowercase() == q)?;
+        if first.is_none() {
+            first = Some(i);
+        } else {
+            gaps += i - last - 1;
+        }
+        last = i;
+    }
+
+    Some(first? * 2 + gaps * 3 + name.len())
+}
+
+fn draw(
+    stdout: &mut Stdout,
+    input: &str,
+    matches: &[(Action, &str, &Binding)],
+    selected: usize,
+) -> Result<()> {
+    let width = matches
+        .iter()
+        .map(|(_, name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+
+    queue!(stdout, MoveTo(0, 1), Clear(ClearType::FromCursorDown))?;
+    for (row, (_, name, binding)) in matches.iter().take(MAX_SHOWN).enumerate() {
+        if row == selected {
+            queue!(stdout, SetAttribute(Attribute::Reverse))?;
+        }
+        queue!(
+            stdout,
+            MoveTo(0, row as u16 + 1),
+            Print(format!("  {:width$}  {}", name, binding, width = width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+    }
+
+    queue!(
+        stdout,
+        MoveTo(0, 0),
+        Clear(ClearType::CurrentLine),
+        Print(":"),
+        Print(input),
+        Show
+    )?;
+    stdout.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use std::{collections::VecDeque, io::Stdout};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{read, Event, KeyCode},
+    execute,
+    style::Print,
+    terminal::{Clear, ClearType},
+};
+use time::OffsetDateTime;
+
+use crate::dates::now;
+
+// Where actions get their text from, so the same action can ask the terminal, take its answers
+// from the command line or be scripted.
+pub trait Input {
+    fn text(&mut self, prompt: &str, default: &str) -> Result<String>;
+}
+
+// Asks on the top line of the terminal
+pub struct TerminalInput<'a>(pub &'a mut Stdout);
+
+impl Input for TerminalInput<'_> {
+    fn text(&mut self, prompt_text: &str, default: &str) -> Result<String> {
+        prompt(self.0, prompt_text, default)
+    }
+}
+
+// Answers prompts in order, then falls back to each prompt's default
+pub struct ScriptedInput {
+    pub answers: VecDeque<String>,
+}
+
+impl ScriptedInput {
+    pub fn new(answers: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            answers: answers.into_iter().collect(),
+        }
+    }
+}
+
+impl Input for ScriptedInput {
+    fn text(&mut self, _prompt: &str, default: &str) -> Result<String> {
+        Ok(self
+            .answers
+            .pop_front()
+            .unwrap_or_else(|| default.to_string()))
+    }
+}
+
+// Answers the first prompts with text given up front, like the argument typed into the command
+// palette, and asks `fallback` for the rest
+pub struct PrefilledInput<'a> {
+    pub answers: VecDeque<String>,
+    pub fallback: &'a mut dyn Input,
+}
+
+impl Input for PrefilledInput<'_> {
+    fn text(&mut self, prompt: &str, default: &str) -> Result<String> {
+        match self.answers.pop_front() {
+            Some(answer) => Ok(answer),
+            None => self.fallback.text(prompt, default),
+        }
+    }
+}
+
+pub fn prompt(stdout: &mut Stdout, prompt: &str, def: &str) -> Result<String> {
+    // disable_raw_mode()?;
+    execute!(stdout, Show)?;
+
+    execute!(
+        stdout,
+        MoveTo(0, 0),
+        Clear(ClearType::CurrentLine),
+        Print(prompt)
+    )?;
+
+    let mut out = def.to_string();
+    let mut cursor_pos = out.len();
+
+    let mut done = false;
+
+    while !done {
+        execute!(
+            stdout,
+            MoveTo(
+                prompt
+                    .len()
+                    .try_into()
+                    .expect("Prompt should be less than 64Ki bytes."),
+                0
+            ),
+            Clear(ClearType::UntilNewLine),
+            Print(&out),
+            MoveTo(
+                (prompt.len() + cursor_pos)
+                    .try_into()
+                    .expect("Prompt should be less than 64Ki bytes."),
+                0
+            ),
+        )?;
+
+        let evt = read()?;
+        if let Event::Key(ke) = evt {
+            if let KeyCode::Char(c) = ke.code {
+                out.insert(cursor_pos, c);
+                cursor_pos += 1;
+            } else if let KeyCode::Backspace = ke.code {
+                if cursor_pos > 0 {
+                    out.remove(cursor_pos - 1);
+                    cursor_pos -= 1;
+                }
+            } else if let KeyCode::Left = ke.code {
+                cursor_pos = cursor_pos.saturating_sub(1);
+            } else if let KeyCode::Right = ke.code {
+                if cursor_pos < out.len() {
+                    cursor_pos += 1;
+                }
+            } else if let KeyCode::Home = ke.code {
+                cursor_pos = 0;
+            } else if let KeyCode::End = ke.code {
+                cursor_pos = out.len();
+            } else if let KeyCode::Enter = ke.code {
+                done = true;
+            }
+        }
+    }
+
+    execute!(stdout, Hide)?;
+    // enable_raw_mode()?;
+
+    Ok(out)
+}
+
+pub fn prompt_date(input: &mut dyn Input) -> Option<OffsetDateTime> {
+    if input.text("Add a due date? (y/n) ", "").ok()? == "y" {
+        let mut current = now();
+
+        let year_input = input.text("Year?  ", &format!("{}", current.year())).ok()?;
+        if !year_input.is_empty() {
+            current = current.replace_year(year_input.parse().ok()?).ok()?;
+        }
+        let month_input = input
+            .text("Month? ", &format!("{}", current.month()))
+            .ok()?;
+        if !month_input.is_empty() {
+            current = current.replace_month(month_input.parse().ok()?).ok()?;
+        }
+        let day_input = input.text("Day? ", &format!("{}", current.day())).ok()?;
+        if !day_input.is_empty() {
+            current = current.replace_day(day_input.parse().ok()?).ok()?;
+        }
+        let hour_input = input.text("Hour? ", &format!("{}", current.hour())).ok()?;
+        if !hour_input.is_empty() {
+            current = current.replace_hour(hour_input.parse().ok()?).ok()?;
+        }
+        let minute_input = input
+            .text("Minute? ", &format!("{}", current.minute()))
+            .ok()?;
+        if !minute_input.is_empty() {
+            current = current.replace_minute(minute_input.parse().ok()?).ok()?;
+        }
+
+        Some(current)
+    } else {
+        None
+    }
+}
+
+pub fn prompt_date_in_place(
+    input: &mut dyn Input,
+    mut current: OffsetDateTime,
+) -> Option<OffsetDateTime> {
+    let choice = input.text("Change due date? (k/y/n) ", "").ok()?;
+    match choice.as_str() {
+        "y" => {
+            let year_input = input.text("Year? ", &format!("{}", current.year())).ok()?;
+            if !year_input.is_empty() {
+                current = current.replace_year(year_input.parse().ok()?).ok()?;
+            }
+            let month_input = input
+                .text("Month? ", &format!("{}", current.month()))
+                .ok()?;
+            if !month_input.is_empty() {
+                current = current.replace_month(month_input.parse().ok()?).ok()?;
+            }
+            let day_input = input.text("Day? ", &format!("{}", current.day())).ok()?;
+            if !day_input.is_empty() {
+                current = current.replace_day(day_input.parse().ok()?).ok()?;
+            }
+            let hour_input = input.text("Hour? ", &format!("{}", current.hour())).ok()?;
+            if !hour_input.is_empty() {
+                current = current.replace_hour(hour_input.parse().ok()?).ok()?;
+            }
+            let minute_input = input
+                .text("Minute? ", &format!("{}", current.minute()))
+                .ok()?;
+            if !minute_input.is_empty() {
+                current = current.replace_minute(minute_input.parse().ok()?).ok()?;
+            }
+
+            Some(current)
+        }
+        "n" => None,
+        _ => {
+            // Keep
+            Some(current)
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    io::{stdin, stdout, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use ron::error::SpannedError;
+
+use crate::{migration::backup_path, todo_config::TodoConfig};
+
+pub enum RecoveryChoice {
+    Edit,   // Open the file in $EDITOR and try again
+    Backup, // Set the broken file aside and restore the newest backup
+    Fresh,  // Set the broken file aside and start from the default config
+    Quit,
+}
+
+// file:line:column, the error and the offending lines with a caret under the column
+pub fn describe_parse_error(config_path: &Path, err: &SpannedError) -> String {
+    let mut out = format!(
+        "{}:{}:{}: {}\n",
+        config_path.display(),
+        err.position.line,
+        err.position.col,
+        err.code
+    );
+
+    if let Ok(source) = std::fs::read_to_string(config_path) {
+        let lines: Vec<&str> = source.lines().collect();
+        let line = err.position.line.max(1);
+        let gutter = format!("{}", line).len();
+
+        for n in line.saturating_sub(2).max(1)..=line.min(lines.len()) {
+            out.push_str(&format!("{:>gutter$} | {}\n", n, lines[n - 1]));
+        }
+        out.push_str(&format!(
+            "{} | {}^\n",
+            " ".repeat(gutter),
+            " ".repeat(err.position.col.saturating_sub(1))
+        ));
+    }
+
+    out
+}
+
+pub fn ask_choice(has_backup: bool) -> Result<RecoveryChoice> {
+    loop {
+        print!(
+            "[e] open in editor, {}[f] start a fresh file, [q] quit: ",
+            if has_backup {
+                "[b] load latest backup, "
+            } else {
+                ""
+            }
+        );
+        stdout().flush()?;
+
+        let mut input = String::new();
+        if stdin().read_line(&mut input)? == 0 {
+            return Ok(RecoveryChoice::Quit);
+        }
+
+        match input.trim() {
+            "e" => return Ok(RecoveryChoice::Edit),
+            "b" if has_backup => return Ok(RecoveryChoice::Backup),
+            "f" => return Ok(RecoveryChoice::Fresh),
+            "q" => return Ok(RecoveryChoice::Quit),
+            _ => {}
+        }
+    }
+}
+
+// Returns Ok(true) if loading should be tried again.
+pub fn recover_from_parse_error(config_path: &Path, err: &SpannedError) -> Result<bool> {
+    eprintln!("Error parsing config file.");
+    eprint!("{}", describe_parse_error(config_path, err));
+
+    let backup = latest_backup(config_path);
+
+    match ask_choice(backup.is_some())? {
+        RecoveryChoice::Edit => {
+            open_in_editor(config_path)?;
+        }
+        RecoveryChoice::Backup => {
+            let backup = backup.ok_or_else(|| anyhow!("No backup found."))?;
+            let moved = set_aside(config_path)?;
+            std::fs::copy(&backup, config_path)?;
+            println!(
+                "Broken file moved to {}, loaded {}",
+                moved.display(),
+                backup.display()
+            );
+        }
+        RecoveryChoice::Fresh => {
+            let moved = set_aside(config_path)?;
+            println!("Broken file moved to {}", moved.display());
+        }
+        RecoveryChoice::Quit => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let status = Command::new(&editor).arg(path).status()?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", editor, status));
+    }
+
+    Ok(())
+}
+
+// Newest backup next to the config file that still reads as RON, either the last file written
+// (<file>.prev.bak) or the one from before a migration (<file>.vN.bak). Unsaved changes dumped
+// on a crash aren't offered, they may be a version of the file that never loaded.
+pub fn latest_backup(config_path: &Path) -> Option<PathBuf> {
+    let name = config_path.file_name()?.to_string_lossy().to_string();
+    let folder = match config_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+
+    std::fs::read_dir(folder)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let file = e.file_name().to_string_lossy().to_string();
+            file.strip_prefix(&name)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .is_some_and(is_backup_tag)
+        })
+        .filter(|e| {
+            std::fs::read_to_string(e.path())
+                .is_ok_and(|source| ron::from_str::<ron::Value>(&source).is_ok())
+        })
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+// "prev" or "v" and a version number
+fn is_backup_tag(tag: &str) -> bool {
+    tag == "prev"
+        || tag
+            .strip_prefix('v')
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+// Writes changes that never got saved next to the config as <file>.unsaved.bak, for when the app
+// goes down with an error or a panic
+pub fn dump_unsaved(config_path: &Path, config: &TodoConfig) -> Result<PathBuf> {
+    let path = backup_path(config_path, "unsaved");
+    std::fs::write(&path, config.to_ron()?)?;
+    Ok(path)
+}
+
+// Renames the file to <file>.broken-<unix time> so nothing overwrites it
+fn set_aside(config_path: &Path) -> Result<PathBuf> {
+    let stamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut name = config_path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(format!(".broken-{}", stamp));
+    let moved = config_path.with_file_name(name);
+
+    std::fs::rename(config_path, &moved)?;
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo_config::new_id;
+
+    use super::*;
+
+    #[test]
+    fn backups() {
+        let folder = std::env::temp_dir().join(format!("dmc-todo-backups-{}", new_id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let config_path = folder.join("todo.ron");
+        let write = |tag: &str, source: &str| {
+            let path = backup_path(&config_path, tag);
+            std::fs::write(&path, source).unwrap();
+            // Far enough apart for the modified times to differ
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            path
+        };
+
+        assert_eq!(latest_backup(&config_path), None);
+        let v0 = write("v0", "(groups: [])");
+        assert_eq!(latest_backup(&config_path), Some(v0));
+        let prev = write("prev", "(version: 3, groups: [])");
+        write("unsaved", "(version: 3, groups: [])");
+        write("broken-1", "(version: 3, groups: [])");
+        assert_eq!(latest_backup(&config_path), Some(prev.clone()));
+        write("v2", "(version: 2, groups: [");
+        assert_eq!(latest_backup(&config_path), Some(prev));
+
+        std::fs::remove_dir_all(folder).ok();
+    }
+}
@@ -0,0 +1,57 @@
+// The cursor of the views that aren't the tree, over the ids of what they list in order. The
+// views keep the id rather than the row so the cursor stays on a todo as the list changes.
+
+// Row of the selected id, the first one if it's gone, None when nothing is listed
+pub fn index(selected: Option<u64>, ids: &[u64]) -> Option<usize> {
+    if ids.is_empty() {
+        return None;
+    }
+    Some(ids.iter().position(|id| Some(*id) == selected).unwrap_or(0))
+}
+
+// Selects the row at index, or the last one when there are fewer
+pub fn select(selected: &mut Option<u64>, ids: &[u64], index: usize) {
+    *selected = ids.get(index.min(ids.len().saturating_sub(1))).copied();
+}
+
+pub fn up(selected: &mut Option<u64>, ids: &[u64]) {
+    if let Some(i) = index(*selected, ids) {
+        select(selected, ids, i.saturating_sub(1));
+    }
+}
+
+pub fn down(selected: &mut Option<u64>, ids: &[u64]) {
+    if let Some(i) = index(*selected, ids) {
+        select(selected, ids, i + 1);
+    }
+}
+
+// To the first row of the run before, like the group or section above. starts(i) says whether
+// row i begins a new run, it is only asked for rows after the first.
+pub fn run_up(selected: &mut Option<u64>, ids: &[u64], starts: impl Fn(usize) -> bool) {
+    let Some(mut i) = index(*selected, ids) else {
+        return;
+    };
+    // Back to the start of this run, then on to the start of the one before
+    while i > 0 && !starts(i) {
+        i -= 1;
+    }
+    if i > 0 {
+        i -= 1;
+        while i > 0 && !starts(i) {
+            i -= 1;
+        }
+    }
+    *selected = Some(ids[i]);
+}
+
+// To the first row of the run after, or the last row when this is the last run
+pub fn run_down(selected: &mut Option<u64>, ids: &[u64], starts: impl Fn(usize) -> bool) {
+    let Some(mut i) = index(*selected, ids) else {
+        return;
+    };
+    while i + 1 < ids.len() && !starts(i + 1) {
+        i += 1;
+    }
+    select(selected, ids, i + 1);
+}
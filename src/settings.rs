@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use time::Duration;
+
+use crate::{
+    custom_commands::CustomCommand,
+    display::{default_due_tiers, Column, Dates, DueWithin, GroupSummary},
+    hooks::HookCommands,
+    keybindings::{Bindings, Keybindings},
+    migration,
+    theme::ThemeChoice,
+    todo_config::ConfigError,
+    workflow::{default_statuses, Status},
+};
+
+pub const SETTINGS_VERSION: u32 = 2; // Version written by this build
+
+// Per-user preferences, kept apart from the todo data so a shared list doesn't carry anyone's keys.
+// Anything missing from settings.ron falls back to the built-in default for that field.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Settings {
+    #[serde(default = "default_version")]
+    pub version: u32, // Hand-written files without a version are read as current
+    #[serde(default = "default_archive_time")]
+    pub archive_time: Duration, // How long a todo should be kept before being archived
+    #[serde(default)]
+    pub theme: ThemeChoice,
+    #[serde(default = "default_due_tiers")]
+    pub due_tiers: Vec<DueWithin>, // When a todo counts as due soon, see the theme's due_tiers
+    #[serde(default)]
+    pub dates: Dates,
+    #[serde(default)]
+    pub columns: Vec<Column>,
+    #[serde(default)]
+    pub group_summary: GroupSummary,
+    #[serde(default)]
+    pub keybindings: Keybindings,
+    #[serde(default)]
+    pub hooks: HookCommands,
+    #[serde(default)]
+    pub custom_commands: Vec<CustomCommand>,
+    #[serde(default = "default_statuses")]
+    pub statuses: Vec<Status>, // Cycled through in order, see workflow.rs
+}
+
+fn default_version() -> u32 {
+    SETTINGS_VERSION
+}
+fn default_archive_time() -> Duration {
+    Duration::days(1)
+}
+
+impl Settings {
+    // settings.ron next to the data file, so a todo file somewhere else has its own
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_file_name("settings.ron")
+    }
+
+    pub fn read_settings(settings_path: &Path) -> Result<Self, ConfigError> {
+        if settings_path.exists() {
+            let source = std::fs::read_to_string(settings_path)?;
+            match migration::upgrade_settings(&source, settings_path)? {
+                Some(upgraded) => {
+                    let settings: Self =
+                        ron::from_str(&upgraded).map_err(|err| ConfigError::Migration {
+                            version: SETTINGS_VERSION,
+                            err,
+                        })?;
+                    settings.write_settings(settings_path)?;
+                    Ok(settings)
+                }
+                None => Ok(ron::from_str(&source)?),
+            }
+        } else {
+            Err(ConfigError::NoConfigFile)
+        }
+    }
+
+    pub fn bindings(&self) -> Bindings<'_> {
+        Bindings::new(&self.keybindings, &self.custom_commands)
+    }
+
+    pub fn write_settings(&self, settings_path: &Path) -> Result<(), ConfigError> {
+        if let Some(parent) = settings_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(
+            settings_path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            archive_time: default_archive_time(),
+            theme: Default::default(),
+            due_tiers: default_due_tiers(),
+            dates: Default::default(),
+            columns: vec![],
+            group_summary: Default::default(),
+            keybindings: Default::default(),
+            hooks: Default::default(),
+            custom_commands: vec![],
+            statuses: default_statuses(),
+        }
+    }
+}
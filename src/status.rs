@@ -0,0 +1,214 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use time::OffsetDateTime;
+
+use crate::{
+    dates::now,
+    debug::DebugInfo,
+    display::{truncate, DueWithin},
+    frame::Frame,
+    keybindings::{KeyPress, KeySequence},
+    navigation::{Cursor, HierarchyItemEnum, PositionHierarchy},
+    settings::Settings,
+    theme::Theme,
+    todo_config::TodoConfig,
+};
+
+// How long a message stays on the bottom line
+const MESSAGE_TIME: Duration = Duration::from_secs(5);
+
+// The bar along the top and the line along the bottom of the list
+pub struct StatusLine {
+    file_name: String,
+    pub dirty: bool,             // Changes that aren't written to the file yet
+    pub pending: Vec<KeyPress>,  // Keys of a chord that isn't finished yet
+    pub running: Option<String>, // Name of the custom command running in the background
+    message: Option<(String, bool, Instant)>, // Text, whether it is an error, when it was set
+    pub debug: Option<DebugInfo>, // Shown over the list when on
+}
+
+impl StatusLine {
+    pub fn new(config_path: &Path) -> Self {
+        Self {
+            file_name: config_path
+                .file_name()
+                .map_or_else(String::new, |n| n.to_string_lossy().to_string()),
+            dirty: false,
+            pending: vec![],
+            running: None,
+            message: None,
+            debug: None,
+        }
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.message = Some((text.into(), false, Instant::now()));
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.message = Some((text.into(), true, Instant::now()));
+    }
+
+    // Drops a message that has been up long enough, returns whether there was one
+    pub fn expire(&mut self) -> bool {
+        let expired = self
+            .message
+            .as_ref()
+            .is_some_and(|(_, _, set)| set.elapsed() >= MESSAGE_TIME);
+        if expired {
+            self.message = None;
+        }
+        expired
+    }
+
+    pub fn draw(
+        &self,
+        frame: &mut Frame,
+        config: &TodoConfig,
+        cursor: &Cursor,
+        settings: &Settings,
+        theme: &Theme,
+    ) {
+        let width = frame.width();
+        let now = now();
+
+        let (breadcrumb, view) = match cursor {
+            Cursor::Hierarchy(h) => {
+                let mut names = h.group_names(config);
+                if let Ok(HierarchyItemEnum::Group(g)) = h.find_item(config).map(|i| i.item) {
+                    names.push(&g.name);
+                }
+                let view = if h.root.is_empty() { "tree" } else { "zoomed" };
+                (names.join(" > "), view.to_string())
+            }
+            Cursor::List(list) => (
+                match list.position(config, settings) {
+                    Some(h) => h.group_names(config).join(" > "),
+                    None => String::new(),
+                },
+                format!("list: {}", list.statuses.join(", ")),
+            ),
+            Cursor::Board(board) => (
+                match PositionHierarchy::find_id(config, board.root) {
+                    Some(h) => {
+                        let mut names = h.group_names(config);
+                        if let Some(root) = board.root_group(config) {
+                            names.push(&root.name);
+                        }
+                        names.join(" > ")
+                    }
+                    None => String::new(),
+                },
+                "board".to_string(),
+            ),
+            Cursor::Calendar(calendar) => (
+                match calendar.position(config) {
+                    Some(h) => h.group_names(config).join(" > "),
+                    None => String::new(),
+                },
+                if calendar.week { "week" } else { "month" }.to_string(),
+            ),
+            Cursor::Agenda(agenda) => (
+                match agenda.position(config) {
+                    Some(h) => h.group_names(config).join(" > "),
+                    None => String::new(),
+                },
+                "agenda".to_string(),
+            ),
+        };
+
+        let (overdue, today) = due_counts(config, now);
+        let mut right = vec![];
+        if overdue > 0 {
+            right.push(format!("{} overdue", overdue));
+        }
+        if today > 0 {
+            right.push(format!("{} due today", today));
+        }
+        right.push(format!(
+            "{}{}",
+            self.file_name,
+            if self.dirty { " [+]" } else { "" }
+        ));
+        right.push(view);
+        let right = format!("{} ", right.join("  "));
+
+        // The breadcrumb gives way first on narrow terminals
+        let room = width.saturating_sub(right.chars().count() + 2);
+        let left = format!(" {}", truncate(&breadcrumb, room));
+        let gap = width.saturating_sub(left.chars().count() + right.chars().count());
+
+        frame.move_to(0, 0);
+        frame.print(
+            truncate(&format!("{}{}{}", left, " ".repeat(gap), right), width),
+            theme.status_bar,
+        );
+
+        let bottom = frame.height().saturating_sub(1);
+        if !self.pending.is_empty() {
+            frame.move_to(0, bottom);
+            frame.print(
+                format!("{} -", KeySequence(self.pending.clone())),
+                theme.status_bar,
+            );
+        } else if let Some((text, error, _)) = self
+            .message
+            .as_ref()
+            .filter(|(_, _, set)| set.elapsed() < MESSAGE_TIME)
+        {
+            frame.move_to(0, bottom);
+            let style = if *error { theme.error } else { theme.todo };
+            frame.print(truncate(text, width), style);
+        } else if let Some(name) = &self.running {
+            frame.move_to(0, bottom);
+            frame.print(
+                truncate(&format!("Running {}…", name), width),
+                theme.summary,
+            );
+        }
+
+        if let Some(debug) = &self.debug {
+            debug.draw(frame, config, cursor, theme);
+        }
+    }
+}
+
+// Open todos and unfinished groups past due, and ones due later today. Snoozed todos wait.
+fn due_counts(config: &TodoConfig, now: OffsetDateTime) -> (usize, usize) {
+    config.groups.iter().fold((0, 0), |counts, group| {
+        group
+            .traverse(
+                (now, counts),
+                |g, _, (now, (overdue, today))| match g.due {
+                    Some(due) if g.todo_count() > 0 => {
+                        if due < now {
+                            (true, (now, (overdue + 1, today)))
+                        } else if DueWithin::Today.contains(due, now) {
+                            (true, (now, (overdue, today + 1)))
+                        } else {
+                            (true, (now, (overdue, today)))
+                        }
+                    }
+                    _ => (true, (now, (overdue, today))),
+                },
+                |t, _, (now, (overdue, today))| match t.due {
+                    Some(due) if t.done_time.is_none() && !t.is_deferred(now) => {
+                        if due < now {
+                            (now, (overdue + 1, today))
+                        } else if DueWithin::Today.contains(due, now) {
+                            (now, (overdue, today + 1))
+                        } else {
+                            (now, (overdue, today))
+                        }
+                    }
+                    _ => (now, (overdue, today)),
+                },
+                |_, _, v| v,
+                0,
+            )
+            .1
+    })
+}
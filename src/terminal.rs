@@ -0,0 +1,68 @@
+use std::{
+    io::stdout,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+// Whether the terminal is ours, so it is only put back once
+static ENTERED: AtomicBool = AtomicBool::new(false);
+
+// Raw mode and the alternate screen for as long as this lives. Dropping it puts the terminal
+// back however the app ends, an error returned with ? included.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        // The guard exists from here on so a failure below still undoes raw mode
+        let guard = Self;
+        ENTERED.store(true, Ordering::SeqCst);
+        execute!(stdout(), EnterAlternateScreen, EnableMouseCapture, Hide)?;
+
+        // A panic message printed on the alternate screen would vanish with it
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            default_hook(info);
+        }));
+
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+        // Panics after this print as usual. Taking the hook mid-panic would abort.
+        if !std::thread::panicking() {
+            let _ = std::panic::take_hook();
+        }
+    }
+}
+
+// Safe to call more than once, only the first call does anything. Clearing again after the panic
+// hook would wipe the message off the main screen.
+fn restore() {
+    if !ENTERED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    execute!(
+        stdout(),
+        Show,
+        DisableMouseCapture,
+        Clear(ClearType::All),
+        LeaveAlternateScreen
+    )
+    .ok();
+    disable_raw_mode().ok();
+}
@@ -0,0 +1,368 @@
+use std::{fmt, io::Stdout};
+
+use crossterm::{
+    queue,
+    style::{Attribute, Color, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor},
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+// A colour in settings.ron. A name ("red", "dark_grey"), "#rrggbb", "ansi_(208)", "rgb_(1,2,3)"
+// or "default" for the terminal's own colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThemeColor(pub Color);
+
+impl Default for ThemeColor {
+    fn default() -> Self {
+        Self(Color::Reset)
+    }
+}
+
+// How one kind of thing is drawn. Anything left out is the terminal default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    pub fg: ThemeColor,
+    pub bg: ThemeColor,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+// Every styled part of the screen. Missing fields in a custom theme come from the default theme.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub cursor: Style,      // The "> " marker
+    pub groups: Vec<Style>, // Group names by depth, the last one is used for anything deeper
+    pub empty_group: Style, // The [0] of a closed group with nothing left to do
+    pub todo: Style,
+    pub done: Style,
+    pub deferred: Style, // Snoozed todos, until their time comes
+    pub overdue: Style,
+    pub due_tiers: Vec<Style>, // For settings' due_tiers in order, the last one is used for the rest
+    pub tag: Style,            // #words in names
+    pub summary: Style,        // The progress column on groups
+    pub status_bar: Style,
+    pub error: Style, // Messages about something that went wrong
+}
+
+// `theme: "monochrome"` picks a built-in theme, `theme: (overdue: (fg: "#ff5f5f"))` makes one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum ThemeChoice {
+    Named(String),
+    Custom(Theme),
+}
+
+impl Default for ThemeChoice {
+    fn default() -> Self {
+        Self::Named("default".to_string())
+    }
+}
+
+impl ThemeChoice {
+    // The theme to draw with. NO_COLOR drops the colours but keeps bold, underline and the like,
+    // and #rrggbb colours are brought down to the 256 colour palette if the terminal lacks truecolor.
+    pub fn resolve(&self) -> Theme {
+        let theme = match self {
+            Self::Named(name) => Theme::named(name).unwrap_or_default(), // Checked when read
+            Self::Custom(theme) => theme.clone(),
+        };
+
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let truecolor = std::env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit");
+
+        theme.map_colors(|color| match color {
+            _ if no_color => Color::Reset,
+            Color::Rgb { r, g, b } if !truecolor => Color::AnsiValue(to_ansi(r, g, b)),
+            c => c,
+        })
+    }
+}
+
+impl Theme {
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "monochrome" => Some(Self::monochrome()),
+            "solarized" => Some(Self::solarized()),
+            _ => None,
+        }
+    }
+
+    // Style for a group name at `depth`
+    pub fn group(&self, depth: usize) -> Style {
+        self.groups
+            .get(depth)
+            .or(self.groups.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Style for something due in the nth tier
+    pub fn due_tier(&self, tier: usize) -> Style {
+        self.due_tiers
+            .get(tier)
+            .or(self.due_tiers.last())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Attributes only, for terminals without colour
+    fn monochrome() -> Self {
+        let plain = Style::default();
+        Self {
+            cursor: Style {
+                bold: true,
+                ..plain
+            },
+            groups: vec![
+                Style {
+                    bold: true,
+                    ..plain
+                },
+                plain,
+            ],
+            empty_group: Style { dim: true, ..plain },
+            todo: plain,
+            done: Style { dim: true, ..plain },
+            deferred: Style {
+                dim: true,
+                italic: true,
+                ..plain
+            },
+            overdue: Style {
+                bold: true,
+                underline: true,
+                ..plain
+            },
+            due_tiers: vec![
+                Style {
+                    bold: true,
+                    ..plain
+                },
+                plain,
+            ],
+            tag: Style {
+                italic: true,
+                ..plain
+            },
+            summary: Style { dim: true, ..plain },
+            status_bar: Style {
+                reverse: true,
+                ..plain
+            },
+            error: Style {
+                bold: true,
+                ..plain
+            },
+        }
+    }
+
+    // Solarized dark accents, needs truecolor to look right
+    fn solarized() -> Self {
+        let rgb = |r, g, b| Style {
+            fg: ThemeColor(Color::Rgb { r, g, b }),
+            ..Style::default()
+        };
+        Self {
+            cursor: Style {
+                bold: true,
+                ..rgb(0xb5, 0x89, 0x00)
+            },
+            groups: vec![
+                Style {
+                    bold: true,
+                    ..rgb(0x26, 0x8b, 0xd2)
+                },
+                rgb(0x2a, 0xa1, 0x98),
+                rgb(0x6c, 0x71, 0xc4),
+            ],
+            empty_group: rgb(0x58, 0x6e, 0x75),
+            todo: rgb(0x93, 0xa1, 0xa1),
+            done: rgb(0x58, 0x6e, 0x75),
+            deferred: Style {
+                italic: true,
+                ..rgb(0x58, 0x6e, 0x75)
+            },
+            overdue: rgb(0xdc, 0x32, 0x2f),
+            due_tiers: vec![rgb(0xcb, 0x4b, 0x16), rgb(0xb5, 0x89, 0x00)],
+            tag: rgb(0xd3, 0x36, 0x82),
+            summary: rgb(0x65, 0x7b, 0x83),
+            status_bar: Style {
+                bg: ThemeColor(Color::Rgb {
+                    r: 0x07,
+                    g: 0x36,
+                    b: 0x42,
+                }),
+                ..rgb(0x93, 0xa1, 0xa1)
+            },
+            error: rgb(0xdc, 0x32, 0x2f),
+        }
+    }
+
+    fn map_colors(mut self, f: impl Fn(Color) -> Color) -> Self {
+        let mut styles: Vec<&mut Style> = vec![
+            &mut self.cursor,
+            &mut self.empty_group,
+            &mut self.todo,
+            &mut self.done,
+            &mut self.deferred,
+            &mut self.overdue,
+            &mut self.tag,
+            &mut self.summary,
+            &mut self.status_bar,
+            &mut self.error,
+        ];
+        styles.extend(self.groups.iter_mut());
+        styles.extend(self.due_tiers.iter_mut());
+        for style in styles {
+            style.fg = ThemeColor(f(style.fg.0));
+            style.bg = ThemeColor(f(style.bg.0));
+        }
+        self
+    }
+}
+
+// The look the app has always had
+impl Default for Theme {
+    fn default() -> Self {
+        let plain = Style::default();
+        let fg = |color| Style {
+            fg: ThemeColor(color),
+            ..plain
+        };
+        Self {
+            cursor: plain,
+            groups: vec![plain],
+            empty_group: fg(Color::DarkGrey),
+            todo: plain,
+            done: fg(Color::DarkGrey),
+            deferred: Style {
+                italic: true,
+                ..fg(Color::DarkGrey)
+            },
+            overdue: fg(Color::Red),
+            due_tiers: vec![fg(Color::Yellow), fg(Color::DarkYellow)],
+            tag: fg(Color::Cyan),
+            summary: plain,
+            status_bar: Style {
+                reverse: true,
+                ..plain
+            },
+            error: fg(Color::Red),
+        }
+    }
+}
+
+impl Style {
+    pub const PLAIN: Style = Style {
+        fg: ThemeColor(Color::Reset),
+        bg: ThemeColor(Color::Reset),
+        bold: false,
+        dim: false,
+        italic: false,
+        underline: false,
+        reverse: false,
+    };
+
+    // Sets the style for whatever is printed next
+    pub fn apply(&self, out: &mut Stdout) {
+        if self.fg.0 != Color::Reset {
+            queue!(out, SetForegroundColor(self.fg.0)).ok();
+        }
+        if self.bg.0 != Color::Reset {
+            queue!(out, SetBackgroundColor(self.bg.0)).ok();
+        }
+        for (on, attribute) in [
+            (self.bold, Attribute::Bold),
+            (self.dim, Attribute::Dim),
+            (self.italic, Attribute::Italic),
+            (self.underline, Attribute::Underlined),
+            (self.reverse, Attribute::Reverse),
+        ] {
+            if on {
+                queue!(out, SetAttribute(attribute)).ok();
+            }
+        }
+    }
+}
+
+pub fn reset(out: &mut Stdout) {
+    queue!(out, SetAttribute(Attribute::Reset), ResetColor).ok();
+}
+
+// Nearest colour in the 6x6x6 cube of the 256 colour palette
+fn to_ansi(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+impl Serialize for ThemeColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Color::Reset => serializer.serialize_str("default"),
+            Color::Rgb { r, g, b } => {
+                serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", r, g, b))
+            }
+            color => color.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if text == "default" || text == "reset" {
+            return Ok(Self(Color::Reset));
+        }
+        if let Some(hex) = text.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+            };
+            return match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Self(Color::Rgb { r, g, b })),
+                _ => Err(de::Error::invalid_value(
+                    de::Unexpected::Str(&text),
+                    &"a colour like #ff8800",
+                )),
+            };
+        }
+        Color::deserialize(de::value::StrDeserializer::<D::Error>::new(&text)).map(Self)
+    }
+}
+
+// By hand rather than untagged so a typo inside a custom theme reports the field it is in
+impl<'de> Deserialize<'de> for ThemeChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ThemeChoice;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a theme name or a theme")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                match Theme::named(name) {
+                    Some(_) => Ok(ThemeChoice::Named(name.to_string())),
+                    None => Err(E::unknown_variant(
+                        name,
+                        &["default", "monochrome", "solarized"],
+                    )),
+                }
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                Theme::deserialize(de::value::MapAccessDeserializer::new(map))
+                    .map(ThemeChoice::Custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
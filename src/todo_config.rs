@@ -1,22 +1,76 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
 
-use crossterm::event::KeyCode;
 use thiserror::Error;
-use time::{Duration, OffsetDateTime};
+use time::OffsetDateTime;
+
+use crate::{dates::now, migration, view_state::ViewState};
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Todo {
+    #[serde(default = "new_id")]
+    pub id: u64, // Stable id, used to find the todo again across edits
     pub name: String,                      // Name of the todo
     pub done_time: Option<OffsetDateTime>, // None if not done
     pub due: Option<OffsetDateTime>,       // None if no due date specified
-    pub created: OffsetDateTime,           // When the todo was created
+    #[serde(default)]
+    pub defer_until: Option<OffsetDateTime>, // Greyed out and left out of counts until then
+    #[serde(default)]
+    pub status: String, // Name of one of settings' statuses, empty for the default one
+    #[serde(default)]
+    pub priority: Priority,
+    pub created: OffsetDateTime, // When the todo was created
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High, // Marked with ! and listed in the agenda even without a due date
+}
+
+impl Priority {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "normal" | "" => Some(Self::Normal),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+impl Todo {
+    pub fn is_deferred(&self, now: OffsetDateTime) -> bool {
+        self.done_time.is_none() && self.defer_until.is_some_and(|until| until > now)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Group {
+    #[serde(default = "new_id")]
+    pub id: u64, // Stable id, used to find the group again across edits
     pub hidden: bool, // Whether the group is hidden or not
     pub name: String, // Name of the group
-    pub open: bool,   // Whether the group is open or not
+    #[serde(default)]
+    pub due: Option<OffsetDateTime>, // For the group as a whole, new todos in it start with it
+    #[serde(skip)]
+    pub open: bool, // Whether the group is open or not (view state, see view_state.rs)
     #[serde(default = "default_todos")]
     pub todos: Vec<Todo>, // Todos in the group
     #[serde(default = "default_todos")]
@@ -77,14 +131,81 @@ impl Group {
         after_handle(self, depth, value)
     }
 
+    // Open todos that aren't deferred
     pub fn todo_count(&self) -> usize {
+        let now = now();
         self.traverse(
-            0,
+            (now, 0),
             |_, _, v| (true, v),
-            |t, _, v| if t.done_time.is_none() { v + 1 } else { v },
+            |t, _, (now, count)| {
+                if t.done_time.is_none() && !t.is_deferred(now) {
+                    (now, count + 1)
+                } else {
+                    (now, count)
+                }
+            },
             |_, _, v| v,
             0,
         )
+        .1
+    }
+
+    // Past its due date with todos still open
+    pub fn is_overdue(&self, now: OffsetDateTime) -> bool {
+        self.due.is_some_and(|due| due < now) && self.todo_count() > 0
+    }
+
+    // Open, done and overdue todos in the group and everything below it.
+    // Overdue groups count toward overdue as well.
+    pub fn progress(&self, now: OffsetDateTime) -> Progress {
+        self.traverse(
+            (now, Progress::default()),
+            |g, _, (now, mut p)| {
+                if g.is_overdue(now) {
+                    p.overdue += 1;
+                }
+                (true, (now, p))
+            },
+            |t, _, (now, mut p)| {
+                if t.is_deferred(now) {
+                    // Not actionable yet, neither open nor done
+                } else if t.done_time.is_some() {
+                    p.done += 1;
+                } else {
+                    p.open += 1;
+                    if t.due.is_some_and(|due| due < now) {
+                        p.overdue += 1;
+                    }
+                }
+                (now, p)
+            },
+            |_, _, v| v,
+            0,
+        )
+        .1
+    }
+
+    // Every todo in the group and below it outside the archives, with the names of the groups
+    // it is in starting from this one
+    pub fn all_todos(&self) -> Vec<(Vec<&str>, &Todo)> {
+        fn walk<'a>(
+            group: &'a Group,
+            path: &mut Vec<&'a str>,
+            out: &mut Vec<(Vec<&'a str>, &'a Todo)>,
+        ) {
+            path.push(&group.name);
+            for subgroup in group.subgroups.iter() {
+                walk(subgroup, path, out);
+            }
+            for todo in group.todos.iter().chain(group.completed.iter()) {
+                out.push((path.clone(), todo));
+            }
+            path.pop();
+        }
+
+        let mut out = vec![];
+        walk(self, &mut vec![], &mut out);
+        out
     }
 
     pub fn is_empty(&self) -> bool {
@@ -98,132 +219,23 @@ impl Group {
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct TodoConfig {
+    #[serde(default)]
+    pub version: u32, // File layout version, see migration.rs
     #[serde(default = "default_groups")]
     pub groups: Vec<Group>,
     #[serde(default = "default_groups")]
     pub archive_groups: Vec<Group>,
-    pub archive_time: Duration, // How long a todo should be kept before being archived
-    pub keybindings: Keybindings,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-pub struct Keybindings {
-    #[serde(default = "default_add_todo")]
-    pub add_todo: KeyCode, //
-    #[serde(default = "default_add_group")]
-    pub add_group: KeyCode, //
-    #[serde(default = "default_add_top_group")]
-    pub add_top_group: KeyCode, //
-    #[serde(default = "default_toggle_group")]
-    pub toggle_group: KeyCode, //
-    #[serde(default = "default_toggle_todo")]
-    pub toggle_todo: KeyCode, //
-    #[serde(default = "default_archive_todo")]
-    pub archive_todo: KeyCode, //
-    #[serde(default = "default_hide_group")]
-    pub hide_group: KeyCode, //
-    #[serde(default = "default_edit_todo")]
-    pub edit_todo: KeyCode, //
-    #[serde(default = "default_edit_group")]
-    pub edit_group: KeyCode, //
-    #[serde(default = "default_move_todo_up")]
-    pub move_todo_up: KeyCode,
-    #[serde(default = "default_move_todo_down")]
-    pub move_todo_down: KeyCode,
-    #[serde(default = "default_move_group_up")]
-    pub move_group_up: KeyCode,
-    #[serde(default = "default_move_group_down")]
-    pub move_group_down: KeyCode,
-    #[serde(default = "default_cursor_up")]
-    pub cursor_up: KeyCode, //
-    #[serde(default = "default_cursor_down")]
-    pub cursor_down: KeyCode, //
-    #[serde(default = "default_group_up")]
-    pub group_up: KeyCode, //
-    #[serde(default = "default_group_down")]
-    pub group_down: KeyCode, //
-    #[serde(default = "default_hierarchy_up")]
-    pub hierarchy_up: KeyCode, //
-    #[serde(default = "default_hierarchy_down")]
-    pub hierarchy_down: KeyCode, //
-    #[serde(default = "default_quit")]
-    pub quit: KeyCode, //
-    #[serde(default = "default_save")]
-    pub save: KeyCode, //
-    #[serde(default = "default_clean")]
-    pub clean: KeyCode,
-    #[serde(default = "default_help")]
-    pub help: KeyCode,
-}
+pub fn new_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
 
-fn default_add_todo() -> KeyCode {
-    KeyCode::Char('a')
-}
-fn default_add_group() -> KeyCode {
-    KeyCode::Char('g')
-}
-fn default_add_top_group() -> KeyCode {
-    KeyCode::Char('n')
-}
-fn default_toggle_group() -> KeyCode {
-    KeyCode::Char(' ')
-}
-fn default_toggle_todo() -> KeyCode {
-    KeyCode::Char(' ')
-}
-fn default_archive_todo() -> KeyCode {
-    KeyCode::Char('d')
-}
-fn default_hide_group() -> KeyCode {
-    KeyCode::Char('x')
-}
-fn default_edit_todo() -> KeyCode {
-    KeyCode::Char('e')
-}
-fn default_edit_group() -> KeyCode {
-    KeyCode::Char('e')
-}
-fn default_move_todo_up() -> KeyCode {
-    KeyCode::Char('i')
-}
-fn default_move_todo_down() -> KeyCode {
-    KeyCode::Char('k')
-}
-fn default_move_group_up() -> KeyCode {
-    KeyCode::Char('i')
-}
-fn default_move_group_down() -> KeyCode {
-    KeyCode::Char('k')
-}
-fn default_cursor_up() -> KeyCode {
-    KeyCode::Up
-}
-fn default_cursor_down() -> KeyCode {
-    KeyCode::Down
-}
-fn default_group_up() -> KeyCode {
-    KeyCode::PageUp
-}
-fn default_group_down() -> KeyCode {
-    KeyCode::PageDown
-}
-fn default_hierarchy_up() -> KeyCode {
-    KeyCode::Char('[')
-}
-fn default_hierarchy_down() -> KeyCode {
-    KeyCode::Char(']')
-}
-fn default_quit() -> KeyCode {
-    KeyCode::Char('q')
-}
-fn default_save() -> KeyCode {
-    KeyCode::Char('s')
-}
-fn default_clean() -> KeyCode {
-    KeyCode::Char('o')
-}
-fn default_help() -> KeyCode {
-    KeyCode::Char('h')
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+
+    now.wrapping_add(COUNTER.fetch_add(1, Ordering::Relaxed))
 }
 
 fn default_groups() -> Vec<Group> {
@@ -233,36 +245,6 @@ fn default_todos() -> Vec<Todo> {
     vec![]
 }
 
-impl Default for Keybindings {
-    fn default() -> Self {
-        Self {
-            add_todo: default_add_todo(), //
-            add_group: default_add_group(),
-            add_top_group: default_add_top_group(),
-            toggle_group: default_toggle_group(), //
-            toggle_todo: default_toggle_todo(),   //
-            archive_todo: default_archive_todo(), //
-            hide_group: default_hide_group(),     //
-            edit_todo: default_edit_todo(),       //
-            edit_group: default_edit_group(),
-            move_todo_up: default_move_todo_up(),
-            move_todo_down: default_move_todo_down(),
-            move_group_up: default_move_group_up(),
-            move_group_down: default_move_group_down(),
-            cursor_up: default_cursor_up(),           //
-            cursor_down: default_cursor_down(),       //
-            group_up: default_group_up(),             //
-            group_down: default_group_down(),         //
-            hierarchy_down: default_hierarchy_down(), //
-            hierarchy_up: default_hierarchy_up(),     //
-            quit: default_quit(),                     //
-            save: default_save(),                     //
-            help: default_help(),
-            clean: default_clean(),
-        }
-    }
-}
-
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("IO error")]
@@ -273,6 +255,13 @@ pub enum ConfigError {
     Stringify(#[from] ron::error::Error), // Should warn the user about possible data loss
     #[error("No config file found")]
     NoConfigFile, // Should generate a new config file
+    #[error("Config file is version {found} but this build only supports up to {supported}")]
+    TooNew { found: u32, supported: u32 }, // Should fail rather than drop fields we don't know
+    #[error("Couldn't read the file once upgraded to version {version}: {err}")]
+    Migration {
+        version: u32,
+        err: ron::error::SpannedError, // Its position is in the upgraded text, not the file
+    },
 }
 
 impl TodoConfig {
@@ -280,10 +269,28 @@ impl TodoConfig {
         Self::default()
     }
 
-    pub fn read_config(config_path: &PathBuf) -> Result<Self, ConfigError> {
+    // Every todo outside the archives with the names of the groups it is in, in tree order
+    pub fn todos(&self) -> Vec<(Vec<&str>, &Todo)> {
+        self.groups.iter().flat_map(Group::all_todos).collect()
+    }
+
+    pub fn read_config(config_path: &PathBuf, settings_path: &Path) -> Result<Self, ConfigError> {
         println!("Config path: {:?}", config_path);
         if config_path.exists() {
-            Ok(ron::from_str(&std::fs::read_to_string(config_path)?)?)
+            let source = std::fs::read_to_string(config_path)?;
+            let state_path = ViewState::path_for(config_path);
+            match migration::upgrade(&source, config_path, settings_path, &state_path)? {
+                Some(upgraded) => {
+                    let config: Self =
+                        ron::from_str(&upgraded).map_err(|err| ConfigError::Migration {
+                            version: migration::CONFIG_VERSION,
+                            err,
+                        })?;
+                    config.write_config(config_path)?;
+                    Ok(config)
+                }
+                None => Ok(ron::from_str(&source)?),
+            }
         } else {
             Err(ConfigError::NoConfigFile)
         }
@@ -291,50 +298,73 @@ impl TodoConfig {
 
     pub fn write_config(&self, config_path: &PathBuf) -> Result<(), ConfigError> {
         std::fs::create_dir_all(config_path.parent().unwrap())?;
-        std::fs::write(
-            config_path,
-            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
-        )?;
+        if config_path.exists() {
+            // Keep the last file we wrote around in case this one gets broken by hand
+            std::fs::copy(config_path, migration::backup_path(config_path, "prev"))?;
+        }
+        std::fs::write(config_path, self.to_ron()?)?;
         Ok(())
     }
+
+    // What write_config puts on disk, compared against the last write to skip unchanged saves
+    pub fn to_ron(&self) -> Result<String, ConfigError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
 }
 
 impl Default for TodoConfig {
     fn default() -> Self {
         Self {
+            version: migration::CONFIG_VERSION,
             groups: vec![Group {
+                id: new_id(),
                 hidden: false,
+                due: None,
                 name: "Welcome".to_string(),
                 open: true,
                 todos: vec![
                     Todo {
+                        id: new_id(),
                         name: "Welcome to todo!".to_string(),
                         done_time: None,
                         due: None,
-                        created: OffsetDateTime::now_local()
-                            .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+                        defer_until: None,
+                        status: String::new(),
+                        priority: Priority::Normal,
+                        created: now(),
                     },
                     Todo {
+                        id: new_id(),
                         name: "Press 'h' for help".to_string(),
                         done_time: None,
                         due: None,
-                        created: OffsetDateTime::now_local()
-                            .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+                        defer_until: None,
+                        status: String::new(),
+                        priority: Priority::Normal,
+                        created: now(),
                     },
                 ],
                 completed: vec![],
                 todo_archive: vec![],
                 subgroups: vec![
                     Group {
+                        id: new_id(),
                         hidden: false,
+                        due: None,
                         name: "Subgroup".to_string(),
                         open: true,
                         todos: vec![Todo {
+                            id: new_id(),
                             name: "This is a subgroup".to_string(),
                             done_time: None,
                             due: None,
-                            created: OffsetDateTime::now_local()
-                                .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+                            defer_until: None,
+                            status: String::new(),
+                            priority: Priority::Normal,
+                            created: now(),
                         }],
                         completed: vec![],
                         todo_archive: vec![],
@@ -342,15 +372,20 @@ impl Default for TodoConfig {
                         subgroup_archive: vec![],
                     },
                     Group {
+                        id: new_id(),
                         hidden: false,
+                        due: None,
                         name: "Another subgroup".to_string(),
                         open: true,
                         todos: vec![Todo {
+                            id: new_id(),
                             name: "This is another subgroup".to_string(),
                             done_time: None,
                             due: None,
-                            created: OffsetDateTime::now_local()
-                                .unwrap_or_else(|_| OffsetDateTime::now_utc()),
+                            defer_until: None,
+                            status: String::new(),
+                            priority: Priority::Normal,
+                            created: now(),
                         }],
                         completed: vec![],
                         todo_archive: vec![],
@@ -361,12 +396,17 @@ impl Default for TodoConfig {
                 subgroup_archive: vec![],
             }],
             archive_groups: vec![],
-            archive_time: Duration::days(1),
-            keybindings: Default::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub open: usize,
+    pub done: usize,
+    pub overdue: usize, // Open todos and unfinished groups past their due date, itself included
+}
+
 pub fn num_to_str(num: usize) -> char {
     if num < 10 {
         char::from_u32(
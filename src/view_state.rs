@@ -0,0 +1,184 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    agenda_view::AgendaView,
+    board_view::BoardView,
+    calendar_view::CalendarView,
+    list_view::ListView,
+    navigation::{Cursor, PositionHierarchy},
+    settings::Settings,
+    todo_config::{ConfigError, TodoConfig},
+};
+
+// How the todo file was last being looked at. Lives in <file>.state.ron next to the data so
+// browsing never dirties the data file itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ViewState {
+    #[serde(default)]
+    pub open_groups: BTreeSet<u64>, // Ids of the groups that are expanded
+    #[serde(default)]
+    pub cursor: Option<u64>, // Id of the item under the cursor
+    #[serde(default)]
+    pub view: ViewMode,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ViewMode {
+    #[default]
+    Tree,
+    Zoomed(u64),       // The tree inside the group with the id
+    List(Vec<String>), // The statuses shown
+    Board {
+        root: u64,
+        column: usize,
+    },
+    // Opens on the day the cursor's todo is due, or today
+    Calendar {
+        week: bool,
+    },
+    Agenda,
+}
+
+impl ViewState {
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        let stem = config_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        config_path.with_file_name(format!("{}.state.ron", stem))
+    }
+
+    // View state is disposable, a missing or broken file just means starting fresh
+    pub fn read_state(state_path: &Path) -> Option<Self> {
+        std::fs::read_to_string(state_path)
+            .ok()
+            .and_then(|source| ron::from_str(&source).ok())
+    }
+
+    // The cursor from the state file. Without one the groups stay as the data has them, like the
+    // open ones in a new default file.
+    pub fn restore(state_path: &Path, config: &mut TodoConfig) -> Cursor {
+        match Self::read_state(state_path) {
+            Some(state) => state.apply(config),
+            None => Cursor::Hierarchy(PositionHierarchy::default()),
+        }
+    }
+
+    pub fn write_state(&self, state_path: &Path) -> Result<(), ConfigError> {
+        std::fs::write(
+            state_path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+
+    pub fn capture(config: &TodoConfig, cursor: &Cursor, settings: &Settings) -> Self {
+        let mut open_groups = BTreeSet::new();
+        for group in config.groups.iter() {
+            open_groups = group.traverse(
+                open_groups,
+                |g, _d, mut v| {
+                    if g.open {
+                        v.insert(g.id);
+                    }
+                    (true, v)
+                },
+                |_t, _d, v| v,
+                |_g, _d, v| v,
+                0,
+            );
+        }
+
+        let cursor_id = cursor
+            .position(config, settings)
+            .and_then(|h| h.find_item(config).ok().map(|item| item.item.id()));
+
+        Self {
+            open_groups,
+            cursor: cursor_id,
+            view: match cursor {
+                Cursor::Hierarchy(h) => match h.top(config) {
+                    [group] if !h.root.is_empty() => ViewMode::Zoomed(group.id),
+                    _ => ViewMode::Tree,
+                },
+                Cursor::List(list) => ViewMode::List(list.statuses.clone()),
+                Cursor::Board(board) => ViewMode::Board {
+                    root: board.root,
+                    column: board.column,
+                },
+                Cursor::Calendar(calendar) => ViewMode::Calendar {
+                    week: calendar.week,
+                },
+                Cursor::Agenda(_) => ViewMode::Agenda,
+            },
+        }
+    }
+
+    // Opens the saved groups and returns the cursor for the saved item, if it still exists
+    pub fn apply(&self, config: &mut TodoConfig) -> Cursor {
+        for group in config.groups.iter_mut() {
+            group.traverse_mut(
+                &self.open_groups,
+                |g, _d, v| {
+                    g.open = v.contains(&g.id);
+                    (true, v)
+                },
+                |_t, _d, v| v,
+                |_g, _d, v| v,
+                0,
+            );
+        }
+
+        let position = self
+            .cursor
+            .and_then(|id| PositionHierarchy::find_id(config, id))
+            .unwrap_or_default();
+
+        // The cursor has to be visible to be drawn in the right place
+        position.reveal(config);
+
+        match &self.view {
+            ViewMode::Tree => Cursor::Hierarchy(position),
+            ViewMode::Zoomed(id) => {
+                Cursor::Hierarchy(match PositionHierarchy::find_id(config, *id) {
+                    Some(root) => position.zoomed(&root.indexes),
+                    None => position,
+                })
+            }
+            ViewMode::List(statuses) => Cursor::List(ListView::new(statuses.clone(), self.cursor)),
+            ViewMode::Board { root, column } => {
+                Cursor::Board(BoardView::new(*root, *column, self.cursor))
+            }
+            ViewMode::Calendar { week } => {
+                Cursor::Calendar(CalendarView::at(config, &position, *week))
+            }
+            ViewMode::Agenda => Cursor::Agenda(AgendaView::new(self.cursor)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::todo_config::new_id;
+
+    use super::*;
+
+    #[test]
+    fn groups_stay_open_without_a_state_file() {
+        let state_path = std::env::temp_dir().join(format!("dmc-todo-{}.state.ron", new_id()));
+        let mut config = TodoConfig::default();
+        assert!(config.groups[0].open);
+
+        ViewState::restore(&state_path, &mut config);
+        assert!(config.groups[0].open);
+
+        // With one only the groups it lists are open
+        ViewState::default().write_state(&state_path).unwrap();
+        ViewState::restore(&state_path, &mut config);
+        assert!(!config.groups[0].open);
+        std::fs::remove_file(state_path).ok();
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Result};
+
+use crate::{
+    action::{archive_todo, toggle_todo},
+    navigation::{HierarchyItemEnum, HierarchyItemEnumMut, PositionHierarchy},
+    todo_config::{Todo, TodoConfig},
+};
+
+// A step a todo can be in, set by `statuses` in settings.ron
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Status {
+    pub name: String,
+    pub glyph: char, // Shown in the checkbox, "[~]"
+    #[serde(default)]
+    pub kind: StatusKind,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StatusKind {
+    #[default]
+    Open, // Still to do, in todos
+    Done,      // In completed, counts as finished
+    Cancelled, // Straight to the archive without counting as finished
+}
+
+pub fn default_statuses() -> Vec<Status> {
+    let status = |name: &str, glyph, kind| Status {
+        name: name.to_string(),
+        glyph,
+        kind,
+    };
+    vec![
+        status("todo", ' ', StatusKind::Open),
+        status("in progress", '~', StatusKind::Open),
+        status("waiting", '?', StatusKind::Open),
+        status("blocked", '!', StatusKind::Open),
+        status("done", '*', StatusKind::Done),
+        status("cancelled", 'x', StatusKind::Cancelled),
+    ]
+}
+
+// The status a todo is in. toggle_todo leaves the name alone, so a name that doesn't fit whether
+// the todo is done falls back to the first status that does.
+pub fn status_of<'a>(todo: &Todo, statuses: &'a [Status]) -> Option<&'a Status> {
+    let kind = if todo.done_time.is_some() {
+        StatusKind::Done
+    } else {
+        StatusKind::Open
+    };
+    statuses
+        .iter()
+        .find(|s| s.name == todo.status && s.kind == kind)
+        .or_else(|| statuses.iter().find(|s| s.kind == kind))
+}
+
+pub fn glyph(todo: &Todo, statuses: &[Status]) -> char {
+    match status_of(todo, statuses) {
+        Some(status) => status.glyph,
+        None if todo.done_time.is_some() => '*',
+        None => ' ',
+    }
+}
+
+pub fn find<'a>(name: &str, statuses: &'a [Status]) -> Result<&'a Status> {
+    statuses
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| {
+            let names: Vec<&str> = statuses.iter().map(|s| s.name.as_str()).collect();
+            anyhow!(
+                "No status \"{}\", the statuses are {}",
+                name.trim(),
+                names.join(", ")
+            )
+        })
+}
+
+// The one after the todo's status, cancelling is left to set_status
+pub fn next<'a>(todo: &Todo, statuses: &'a [Status]) -> Option<&'a Status> {
+    let cycle: Vec<&Status> = statuses
+        .iter()
+        .filter(|s| s.kind != StatusKind::Cancelled)
+        .collect();
+    let current = status_of(todo, statuses)
+        .and_then(|current| cycle.iter().position(|s| *s == current))
+        .map_or(0, |i| i + 1);
+    cycle.get(current % cycle.len().max(1)).copied()
+}
+
+// Puts the todo under the cursor in the status, moving it between todos, completed and the archive
+// to match. The cursor follows it unless it was archived.
+pub fn set_status(
+    config: &mut TodoConfig,
+    h: &mut PositionHierarchy,
+    status: &Status,
+) -> Result<()> {
+    let HierarchyItemEnum::Todo(t) = h.find_item(config)?.item else {
+        return Ok(());
+    };
+    let (id, done) = (t.id, t.done_time.is_some());
+
+    if status.kind == StatusKind::Cancelled {
+        if let HierarchyItemEnumMut::Todo(t) = h.find_item_mut(config)?.item {
+            t.status = status.name.clone();
+            t.done_time = None;
+        }
+        return archive_todo(config, h);
+    }
+
+    if done != (status.kind == StatusKind::Done) {
+        toggle_todo(config, h)?;
+        h.refind(config, id);
+    }
+    if let HierarchyItemEnumMut::Todo(t) = h.find_item_mut(config)?.item {
+        t.status = status.name.clone();
+    }
+
+    Ok(())
+}